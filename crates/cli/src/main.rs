@@ -1,9 +1,15 @@
 mod error;
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use clap::{Parser, Subcommand};
-use img_stegano::{decode_from_path, encode_from_path, Image, ImageFormat, ImgSteganoError};
+use clap::{Parser, Subcommand, ValueEnum};
+use img_stegano::{
+    decode_bytes_with_options, decode_encrypted, decode_join, decode_pvd, decode_with_report,
+    encode_archive, encode_bytes_with_options, encode_encrypted, encode_file, encode_pvd,
+    encode_reversible, encode_shares, encode_split, extract_and_restore, extract_archive,
+    extract_entry, extract_file, list_archive, recover_from_images, Channels, Compression,
+    DecodeOptions, Distortion, EncodeOptions, HiddenFile, Image, ImageFormat, ImgSteganoError,
+};
 
 #[derive(Parser, Debug)]
 #[command(name = "img_stegano_cli")]
@@ -24,56 +30,529 @@ enum Commands {
         /// Text message to encode
         #[arg(short, long)]
         message: String,
+
+        /// Encrypt the message with this password
+        #[arg(short, long)]
+        password: Option<String>,
+
+        /// Compress the message before encoding it
+        #[arg(short, long, value_enum, conflicts_with = "password")]
+        compress: Option<CompressionArg>,
+
+        /// Reed-Solomon parity bytes per 255 byte block (repairs up to half as many corrupted bytes)
+        #[arg(short, long, conflicts_with = "password")]
+        error_correction: Option<u8>,
+
+        /// Low bits of each channel to use, from 1 to 4, or 8 for 16-bit images (more bits, more capacity)
+        #[arg(short, long, conflicts_with = "password")]
+        bits_per_channel: Option<u8>,
+
+        /// Color channels to embed into, any of the letters r, g, b and a (e.g. "b")
+        #[arg(long, value_parser = parse_channels, conflicts_with = "password")]
+        channels: Option<Channels>,
+
+        /// Stego key scattering the message across the image (needed again to decode)
+        #[arg(short, long, conflicts_with = "password")]
+        key: Option<String>,
+
+        /// Move samples up or down by one instead of replacing their low bits
+        #[arg(long, conflicts_with = "password")]
+        lsb_matching: bool,
+
+        /// Use Hamming codes to change fewer samples (for short messages)
+        #[arg(long, conflicts_with_all = ["password", "error_correction"])]
+        matrix_embedding: bool,
+
+        /// Prefer textured regions of the image over flat ones
+        #[arg(long, conflicts_with_all = ["password", "error_correction", "lsb_matching"])]
+        adaptive: bool,
+
+        /// Use syndrome-trellis codes, changing the samples that cost least under this cost function
+        #[arg(long, value_enum, conflicts_with_all = ["password", "error_correction", "matrix_embedding"])]
+        stc: Option<DistortionArg>,
     },
     /// Decode text from an image
     Decode {
         /// Input image path
         #[arg(short, long)]
         input: PathBuf,
+
+        /// Password the message was encrypted with
+        #[arg(short, long)]
+        password: Option<String>,
+
+        /// Stego key the message was encoded with
+        #[arg(short, long, conflicts_with = "password")]
+        key: Option<String>,
+    },
+    /// Hide a file, keeping its name and metadata (outputs PNG format)
+    EncodeFile {
+        /// Input image path
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// File to hide
+        #[arg(short, long)]
+        file: PathBuf,
+    },
+    /// Extract a hidden file under its original name
+    ExtractFile {
+        /// Input image path
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Directory to write the file to
+        #[arg(short, long, default_value = ".")]
+        output_dir: PathBuf,
+    },
+    /// Hide several files in one image (outputs PNG format)
+    EncodeArchive {
+        /// Input image path
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Files to hide, may be given multiple times
+        #[arg(short, long = "file", required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// List the files in a hidden archive
+    ListArchive {
+        /// Input image path
+        #[arg(short, long)]
+        input: PathBuf,
+    },
+    /// Extract files from a hidden archive
+    ExtractArchive {
+        /// Input image path
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Only extract the entry with this name
+        #[arg(short, long)]
+        entry: Option<String>,
+
+        /// Directory to write the files to
+        #[arg(short, long, default_value = ".")]
+        output_dir: PathBuf,
+    },
+    /// Split a file across several images (outputs PNG format)
+    EncodeSplit {
+        /// Cover image paths, may be given multiple times
+        #[arg(short, long = "input", required = true)]
+        inputs: Vec<PathBuf>,
+
+        /// File to hide
+        #[arg(short, long)]
+        file: PathBuf,
+    },
+    /// Reassemble a file split across several images
+    DecodeJoin {
+        /// Encoded image paths in any order, may be given multiple times
+        #[arg(short, long = "input", required = true)]
+        inputs: Vec<PathBuf>,
+
+        /// Path to write the reassembled file to
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// Share a file across several images so any `threshold` of them recover it
+    EncodeShares {
+        /// Cover image paths, may be given multiple times
+        #[arg(short, long = "input", required = true)]
+        inputs: Vec<PathBuf>,
+
+        /// File to hide
+        #[arg(short, long)]
+        file: PathBuf,
+
+        /// Number of images needed to recover the file
+        #[arg(short, long)]
+        threshold: u8,
+    },
+    /// Recover a file from enough of its shares
+    Recover {
+        /// Encoded image paths in any order, may be given multiple times
+        #[arg(short, long = "input", required = true)]
+        inputs: Vec<PathBuf>,
+
+        /// Path to write the recovered file to
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// Encode text so the original image can be restored exactly (outputs PNG format)
+    EncodeReversible {
+        /// Input image path
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Text message to encode
+        #[arg(short, long)]
+        message: String,
+    },
+    /// Decode text encoded reversibly and restore the original image
+    Restore {
+        /// Input image path
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Path to write the restored image to (PNG format)
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// Encode text with pixel value differencing, more bits where the image varies (outputs PNG format)
+    EncodePvd {
+        /// Input image path
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Text message to encode
+        #[arg(short, long)]
+        message: String,
+    },
+    /// Decode text encoded with pixel value differencing
+    DecodePvd {
+        /// Input image path
+        #[arg(short, long)]
+        input: PathBuf,
     },
     /// Get the maximum message capacity for an image
     Capacity {
         /// Input image path
         #[arg(short, long)]
         input: PathBuf,
+
+        /// Low bits of each channel to use, from 1 to 4, or 8 for 16-bit images
+        #[arg(short, long, default_value_t = 1)]
+        bits_per_channel: u8,
+
+        /// Color channels to embed into, any of the letters r, g, b and a
+        #[arg(long, value_parser = parse_channels)]
+        channels: Option<Channels>,
+
+        /// Report the capacity for pixel value differencing instead, which depends on the content
+        #[arg(long, conflicts_with_all = ["bits_per_channel", "channels"])]
+        pvd: bool,
     },
 }
 
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum CompressionArg {
+    Deflate,
+    Zstd,
+}
+
+impl From<CompressionArg> for Compression {
+    fn from(value: CompressionArg) -> Self {
+        match value {
+            CompressionArg::Deflate => Compression::Deflate,
+            CompressionArg::Zstd => Compression::Zstd,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum DistortionArg {
+    Uniform,
+    Hill,
+    Adaptive,
+}
+
+impl From<DistortionArg> for Distortion {
+    fn from(value: DistortionArg) -> Self {
+        match value {
+            DistortionArg::Uniform => Distortion::Uniform,
+            DistortionArg::Hill => Distortion::Hill,
+            DistortionArg::Adaptive => Distortion::Adaptive,
+        }
+    }
+}
+
 fn main() -> Result<(), ImgSteganoError> {
     let cli = Cli::parse();
 
     match cli.command {
-        Commands::Encode { input, message } => {
+        Commands::Encode {
+            input,
+            message,
+            password,
+            compress,
+            error_correction,
+            bits_per_channel,
+            channels,
+            key,
+            lsb_matching,
+            matrix_embedding,
+            adaptive,
+            stc,
+        } => {
             println!("Encoding message into image...");
-            let encoded_image = encode_from_path(&input, &message)?;
-
-            let output = input
-                .file_stem()
-                .and_then(|stem| stem.to_str())
-                .map(|stem| {
-                    let mut path = input.clone();
-                    path.set_file_name(format!("{stem}-encoded.png"));
-                    path
-                })
-                .unwrap_or_else(|| PathBuf::from("encoded.png"));
+            let image = Image::open(&input)?;
+            let encoded_image = match password {
+                Some(password) => encode_encrypted(image, message.as_bytes(), &password)?,
+                None => {
+                    let mut options = EncodeOptions::new()
+                        .with_compression(compress.map(Into::into).unwrap_or_default())
+                        .with_error_correction(error_correction.unwrap_or_default())
+                        .with_bits_per_channel(bits_per_channel.unwrap_or(1))
+                        .with_channels(channels.unwrap_or_default())
+                        .with_lsb_matching(lsb_matching)
+                        .with_matrix_embedding(matrix_embedding)
+                        .with_adaptive_embedding(adaptive);
+                    if let Some(key) = key {
+                        options = options.with_stego_key(key.as_bytes());
+                    }
+                    if let Some(distortion) = stc {
+                        options = options.with_syndrome_trellis(distortion.into());
+                    }
+                    encode_bytes_with_options(image, message.as_bytes(), &options)?
+                }
+            };
 
+            let output = encoded_output_path(&input);
             encoded_image.save(&output, ImageFormat::Png)?;
             println!(
                 "✓ Text encoded image saved to: {} (PNG format)",
                 output.display()
             );
         }
-        Commands::Decode { input } => {
+        Commands::Decode {
+            input,
+            password,
+            key,
+        } => {
             println!("Decoding message from image...");
-            let decoded = decode_from_path(input)?;
+            let image = Image::open(input)?;
+            let decoded = match (password, key) {
+                (Some(password), _) => String::from_utf8(decode_encrypted(&image, &password)?)
+                    .map_err(|_| ImgSteganoError::InvalidUtf8)?,
+                (None, Some(key)) => {
+                    let options = DecodeOptions::new().with_stego_key(key.as_bytes());
+                    String::from_utf8(decode_bytes_with_options(&image, &options)?)
+                        .map_err(|_| ImgSteganoError::InvalidUtf8)?
+                }
+                (None, None) => {
+                    let (payload, report) = decode_with_report(&image)?;
+                    if report.corrected_errors > 0 {
+                        println!("✓ Corrected {} damaged bytes", report.corrected_errors);
+                    }
+                    String::from_utf8(payload).map_err(|_| ImgSteganoError::InvalidUtf8)?
+                }
+            };
             println!("✓ Decoded Text:");
             println!("{decoded}");
         }
-        Commands::Capacity { input } => {
+        Commands::EncodeFile { input, file } => {
+            println!("Encoding file into image...");
+            let hidden = HiddenFile::from_path(&file)?;
+            let encoded_image = encode_file(Image::open(&input)?, &hidden)?;
+
+            let output = encoded_output_path(&input);
+            encoded_image.save(&output, ImageFormat::Png)?;
+            println!(
+                "✓ File {} ({}, {} bytes) encoded into: {} (PNG format)",
+                hidden.name,
+                hidden.mime,
+                hidden.data.len(),
+                output.display()
+            );
+        }
+        Commands::ExtractFile { input, output_dir } => {
+            println!("Extracting file from image...");
+            let hidden = extract_file(&Image::open(input)?)?;
+            let written = hidden.write_to_dir(output_dir)?;
+            println!(
+                "✓ Extracted {} ({}, {} bytes) to: {}",
+                hidden.name,
+                hidden.mime,
+                hidden.data.len(),
+                written.display()
+            );
+        }
+        Commands::EncodeArchive { input, files } => {
+            println!("Encoding archive into image...");
+            let hidden = files
+                .iter()
+                .map(HiddenFile::from_path)
+                .collect::<Result<Vec<_>, _>>()?;
+            let encoded_image = encode_archive(Image::open(&input)?, &hidden)?;
+
+            let output = encoded_output_path(&input);
+            encoded_image.save(&output, ImageFormat::Png)?;
+            println!(
+                "✓ {} files encoded into: {} (PNG format)",
+                hidden.len(),
+                output.display()
+            );
+        }
+        Commands::ListArchive { input } => {
+            for entry in list_archive(&Image::open(input)?)? {
+                println!("{}\t{}\t{} bytes", entry.name, entry.mime, entry.size);
+            }
+        }
+        Commands::ExtractArchive {
+            input,
+            entry,
+            output_dir,
+        } => {
+            println!("Extracting archive from image...");
+            let image = Image::open(input)?;
+            let hidden = match entry {
+                Some(name) => vec![extract_entry(&image, &name)?],
+                None => extract_archive(&image)?,
+            };
+            for file in &hidden {
+                let written = file.write_to_dir(&output_dir)?;
+                println!("✓ Extracted {} to: {}", file.name, written.display());
+            }
+        }
+        Commands::EncodeSplit { inputs, file } => {
+            println!("Splitting file across {} images...", inputs.len());
+            let covers = inputs
+                .iter()
+                .map(Image::open)
+                .collect::<Result<Vec<_>, _>>()?;
+            let shards = encode_split(
+                covers,
+                &std::fs::read(&file).map_err(ImgSteganoError::from)?,
+            )?;
+            for (input, shard) in inputs.iter().zip(shards) {
+                let output = encoded_output_path(input);
+                shard.save(&output, ImageFormat::Png)?;
+                println!("✓ Shard saved to: {} (PNG format)", output.display());
+            }
+        }
+        Commands::DecodeJoin { inputs, output } => {
+            println!("Joining {} images...", inputs.len());
+            let images = inputs
+                .iter()
+                .map(Image::open)
+                .collect::<Result<Vec<_>, _>>()?;
+            let payload = decode_join(&images)?;
+            std::fs::write(&output, &payload).map_err(ImgSteganoError::from)?;
+            println!(
+                "✓ Reassembled {} bytes to: {}",
+                payload.len(),
+                output.display()
+            );
+        }
+        Commands::EncodeShares {
+            inputs,
+            file,
+            threshold,
+        } => {
+            println!(
+                "Sharing file across {} images ({threshold} needed to recover)...",
+                inputs.len()
+            );
+            let covers = inputs
+                .iter()
+                .map(Image::open)
+                .collect::<Result<Vec<_>, _>>()?;
+            let payload = std::fs::read(&file).map_err(ImgSteganoError::from)?;
+            let shares = encode_shares(covers, &payload, threshold)?;
+            for (input, share) in inputs.iter().zip(shares) {
+                let output = encoded_output_path(input);
+                share.save(&output, ImageFormat::Png)?;
+                println!("✓ Share saved to: {} (PNG format)", output.display());
+            }
+        }
+        Commands::Recover { inputs, output } => {
+            println!("Recovering from {} images...", inputs.len());
+            let images = inputs
+                .iter()
+                .map(Image::open)
+                .collect::<Result<Vec<_>, _>>()?;
+            let payload = recover_from_images(&images)?;
+            std::fs::write(&output, &payload).map_err(ImgSteganoError::from)?;
+            println!(
+                "✓ Recovered {} bytes to: {}",
+                payload.len(),
+                output.display()
+            );
+        }
+        Commands::EncodeReversible { input, message } => {
+            println!("Encoding message reversibly into image...");
+            let encoded_image = encode_reversible(Image::open(&input)?, message.as_bytes())?;
+
+            let output = encoded_output_path(&input);
+            encoded_image.save(&output, ImageFormat::Png)?;
+            println!(
+                "✓ Text encoded image saved to: {} (PNG format)",
+                output.display()
+            );
+        }
+        Commands::Restore { input, output } => {
+            println!("Decoding message and restoring image...");
+            let (payload, restored) = extract_and_restore(&Image::open(input)?)?;
+            restored.save(&output, ImageFormat::Png)?;
+            println!("✓ Original image restored to: {}", output.display());
+            println!("✓ Decoded Text:");
+            println!(
+                "{}",
+                String::from_utf8(payload).map_err(|_| ImgSteganoError::InvalidUtf8)?
+            );
+        }
+        Commands::EncodePvd { input, message } => {
+            println!("Encoding message into image with PVD...");
+            let encoded_image = encode_pvd(Image::open(&input)?, message.as_bytes())?;
+
+            let output = encoded_output_path(&input);
+            encoded_image.save(&output, ImageFormat::Png)?;
+            println!(
+                "✓ Text encoded image saved to: {} (PNG format)",
+                output.display()
+            );
+        }
+        Commands::DecodePvd { input } => {
+            println!("Decoding message from image with PVD...");
+            let payload = decode_pvd(&Image::open(input)?)?;
+            println!("✓ Decoded Text:");
+            println!(
+                "{}",
+                String::from_utf8(payload).map_err(|_| ImgSteganoError::InvalidUtf8)?
+            );
+        }
+        Commands::Capacity {
+            input,
+            bits_per_channel,
+            channels,
+            pvd,
+        } => {
             let image = Image::open(input)?;
-            let capacity = image.capacity();
+            let capacity = if pvd {
+                image.pvd_capacity()
+            } else {
+                let options = EncodeOptions::new()
+                    .with_bits_per_channel(bits_per_channel)
+                    .with_channels(channels.unwrap_or_default());
+                image.capacity_with_options(&options)
+            };
             println!("✓ Image capacity: {capacity} bytes (~{capacity} characters)");
         }
     }
     Ok(())
 }
+
+/// Parse a channel selection such as "rgb" or "b"
+fn parse_channels(value: &str) -> Result<Channels, String> {
+    value
+        .chars()
+        .map(|letter| match letter.to_ascii_lowercase() {
+            'r' => Ok(Channels::RED),
+            'g' => Ok(Channels::GREEN),
+            'b' => Ok(Channels::BLUE),
+            'a' => Ok(Channels::ALPHA),
+            _ => Err(format!("unknown channel '{letter}', expected r, g, b or a")),
+        })
+        .reduce(|channels, channel| Ok(channels? | channel?))
+        .unwrap_or_else(|| Err("no channels given".to_string()))
+}
+
+/// Output path next to the input image, e.g. `dice.png` -> `dice-encoded.png`
+fn encoded_output_path(input: &Path) -> PathBuf {
+    input
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .map(|stem| input.with_file_name(format!("{stem}-encoded.png")))
+        .unwrap_or_else(|| PathBuf::from("encoded.png"))
+}
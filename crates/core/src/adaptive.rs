@@ -0,0 +1,88 @@
+use std::cmp::Reverse;
+
+use image::{DynamicImage, GenericImageView};
+
+use crate::lsb::{self, Layout, Samples};
+use crate::traversal::StegoKey;
+use crate::ImgSteganoError;
+
+/// Body slots follow the header in traversal order
+pub(crate) const COST_MAP_NONE: u8 = 0;
+
+/// Cost map scoring each sample by the variance of its 3x3 neighbourhood
+pub(crate) const COST_MAP_VARIANCE: u8 = 1;
+
+/// Slots left for the body after a `header_size` byte header, most textured first
+///
+/// The header's first `prefix_size` bytes sit elsewhere, see
+/// [`lsb::stream_slots`].
+///
+/// Texture is measured on the bits above the payload bits, which embedding
+/// never touches, so the decoder recomputes the same order from the encoded
+/// image. Equally textured slots keep their traversal order.
+pub(crate) fn body_slots(
+    image: &DynamicImage,
+    layout: Layout,
+    key: Option<&StegoKey>,
+    prefix_size: usize,
+    header_size: usize,
+    cost_map: u8,
+) -> Result<Vec<usize>, ImgSteganoError> {
+    if cost_map != COST_MAP_VARIANCE {
+        return Err(ImgSteganoError::UnsupportedCostMap { cost_map });
+    }
+    // a forged header may be shorter than the prefix it claims to follow
+    let rest = header_size
+        .checked_sub(prefix_size)
+        .ok_or(ImgSteganoError::NoPayloadFound)?;
+    let header_slots = (rest * 8).div_ceil(layout.bits_per_channel as usize);
+    let mut slots: Vec<usize> = lsb::stream_slots(image, layout, key, prefix_size)
+        .skip(header_slots)
+        .collect();
+
+    let samples = Samples::new(image);
+    let (width, height) = image.dimensions();
+    let plane = Plane {
+        samples: &samples,
+        width: width as usize,
+        height: height as usize,
+        samples_per_pixel: image.color().channel_count() as usize,
+        shift: layout.bits_per_channel,
+    };
+    slots.sort_by_cached_key(|&index| Reverse(plane.variance(index)));
+    Ok(slots)
+}
+
+/// One color plane of an image with the payload bits masked off
+struct Plane<'a> {
+    samples: &'a Samples<'a>,
+    width: usize,
+    height: usize,
+    samples_per_pixel: usize,
+    shift: u8,
+}
+
+impl Plane<'_> {
+    /// Variance of the 3x3 neighbourhood around a sample, times 81
+    ///
+    /// Neighbours outside the image are left out.
+    fn variance(&self, index: usize) -> u64 {
+        let pixel = index / self.samples_per_pixel;
+        let channel = index % self.samples_per_pixel;
+        let (x, y) = (pixel % self.width, pixel / self.width);
+
+        let (mut count, mut sum, mut squares) = (0u64, 0u64, 0u64);
+        for ny in y.saturating_sub(1)..=(y + 1).min(self.height - 1) {
+            for nx in x.saturating_sub(1)..=(x + 1).min(self.width - 1) {
+                let sample = self
+                    .samples
+                    .get((ny * self.width + nx) * self.samples_per_pixel + channel);
+                let value = (sample >> self.shift) as u64;
+                count += 1;
+                sum += value;
+                squares += value * value;
+            }
+        }
+        (count * squares - sum * sum) * 81 / (count * count)
+    }
+}
@@ -0,0 +1,209 @@
+use std::time::SystemTime;
+
+use crate::compress::{compress, decompress, decompress_prefix};
+use crate::decode::{ensure_plaintext, extract_with_options, read_header_with_options};
+use crate::encode::embed;
+use crate::file::{read_fields, take, write_fields};
+use crate::header::{Header, Metadata};
+use crate::{DecodeOptions, EncodeOptions, HiddenFile, Image, ImgSteganoError};
+
+/// Metadata kind of an archive of several files
+pub(crate) const KIND_ARCHIVE: u8 = 2;
+
+/// Description of one file stored in an archive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveEntry {
+    pub name: String,
+    pub mime: String,
+    pub modified: Option<SystemTime>,
+    /// Size of the entry's contents in bytes
+    pub size: usize,
+}
+
+/// Hide several files in one image
+///
+/// The entry index is stored in the payload header, so it can be listed with
+/// [`list_archive`] without unpacking any file contents.
+pub fn encode_archive(input_image: Image, files: &[HiddenFile]) -> Result<Image, ImgSteganoError> {
+    encode_archive_with_options(input_image, files, &EncodeOptions::default())
+}
+
+/// Hide several files in one image with the given options
+pub fn encode_archive_with_options(
+    Image(input_image): Image,
+    files: &[HiddenFile],
+    options: &EncodeOptions,
+) -> Result<Image, ImgSteganoError> {
+    let data: Vec<u8> = files.iter().flat_map(|file| file.data.clone()).collect();
+    // Validate archive is not empty
+    if data.is_empty() {
+        return Err(ImgSteganoError::EmptyMessage);
+    }
+
+    let metadata = Metadata {
+        kind: KIND_ARCHIVE,
+        record: index(files)?,
+    };
+    let (header, body) = compress(&data, options.compression)?;
+    embed(input_image, header.with_metadata(metadata), &body, options)
+}
+
+/// List the files in an archive without unpacking their contents
+pub fn list_archive(encoded_image: &Image) -> Result<Vec<ArchiveEntry>, ImgSteganoError> {
+    list_archive_with_options(encoded_image, &DecodeOptions::default())
+}
+
+/// List the files in an archive hidden with a stego key
+///
+/// Only the header is read, so the listing neither checks the body's
+/// checksum nor depends on it being intact.
+pub fn list_archive_with_options(
+    encoded_image: &Image,
+    options: &DecodeOptions,
+) -> Result<Vec<ArchiveEntry>, ImgSteganoError> {
+    let header = read_header_with_options(encoded_image, options)?;
+    entries(&header)
+}
+
+/// Extract every file from an archive hidden with [`encode_archive`]
+pub fn extract_archive(encoded_image: &Image) -> Result<Vec<HiddenFile>, ImgSteganoError> {
+    extract_archive_with_options(encoded_image, &DecodeOptions::default())
+}
+
+/// Extract every file from an archive hidden with a stego key
+pub fn extract_archive_with_options(
+    encoded_image: &Image,
+    options: &DecodeOptions,
+) -> Result<Vec<HiddenFile>, ImgSteganoError> {
+    let (header, body, _) = extract_with_options(encoded_image, options)?;
+    let entries = entries(&header)?;
+    ensure_plaintext(&header)?;
+    let mut data = decompress(&header, body)?;
+
+    // Entries are stored back to back, so split them off the end in reverse
+    let mut files: Vec<HiddenFile> = entries
+        .into_iter()
+        .rev()
+        .map(|entry| HiddenFile {
+            data: data.split_off(data.len() - entry.size),
+            name: entry.name,
+            mime: entry.mime,
+            modified: entry.modified,
+        })
+        .collect();
+    files.reverse();
+    Ok(files)
+}
+
+/// Extract a single file from an archive by name
+///
+/// Returns [`ImgSteganoError::EntryNotFound`] if no entry has that name.
+pub fn extract_entry(encoded_image: &Image, name: &str) -> Result<HiddenFile, ImgSteganoError> {
+    extract_entry_with_options(encoded_image, name, &DecodeOptions::default())
+}
+
+/// Extract a single file by name from an archive hidden with a stego key
+///
+/// The whole body is read to verify its checksum, but a compressed archive
+/// is only decompressed as far as the end of the entry.
+pub fn extract_entry_with_options(
+    encoded_image: &Image,
+    name: &str,
+    options: &DecodeOptions,
+) -> Result<HiddenFile, ImgSteganoError> {
+    let (header, body, _) = extract_with_options(encoded_image, options)?;
+    let entries = entries(&header)?;
+    let offset: usize = entries
+        .iter()
+        .take_while(|entry| entry.name != name)
+        .map(|entry| entry.size)
+        .sum();
+    let entry = entries
+        .into_iter()
+        .find(|entry| entry.name == name)
+        .ok_or_else(|| ImgSteganoError::EntryNotFound {
+            name: name.to_string(),
+        })?;
+
+    ensure_plaintext(&header)?;
+    let mut data = decompress_prefix(&header, body, offset + entry.size)?;
+    Ok(HiddenFile {
+        data: data.split_off(offset),
+        name: entry.name,
+        mime: entry.mime,
+        modified: entry.modified,
+    })
+}
+
+/// Serialize the entry index: entry count, then each entry's fields and size
+fn index(files: &[HiddenFile]) -> Result<Vec<u8>, ImgSteganoError> {
+    if files.len() > u16::MAX as usize {
+        return Err(ImgSteganoError::InvalidFileMetadata {
+            reason: "archive holds at most 65535 entries",
+        });
+    }
+
+    let mut record = (files.len() as u16).to_be_bytes().to_vec();
+    for (i, file) in files.iter().enumerate() {
+        if files[..i].iter().any(|other| other.name == file.name) {
+            return Err(ImgSteganoError::InvalidFileMetadata {
+                reason: "archive entry names must be unique",
+            });
+        }
+        let size =
+            u32::try_from(file.data.len()).map_err(|_| ImgSteganoError::InvalidFileMetadata {
+                reason: "archive entry is larger than 4 GiB",
+            })?;
+        write_fields(&mut record, &file.name, &file.mime, file.modified)?;
+        record.extend_from_slice(&size.to_be_bytes());
+    }
+
+    // The metadata record length is stored in two bytes
+    if record.len() > u16::MAX as usize {
+        return Err(ImgSteganoError::InvalidFileMetadata {
+            reason: "archive index exceeds 65535 bytes",
+        });
+    }
+    Ok(record)
+}
+
+/// Parse the entry index from an archive header
+fn entries(header: &Header) -> Result<Vec<ArchiveEntry>, ImgSteganoError> {
+    let record = match &header.metadata {
+        Some(metadata) if metadata.kind == KIND_ARCHIVE => &metadata.record,
+        _ => return Err(ImgSteganoError::NotAnArchive),
+    };
+    let corrupt = || ImgSteganoError::InvalidFileMetadata {
+        reason: "archive index is malformed",
+    };
+
+    let mut fields = record.as_slice();
+    let count = take(&mut fields, 2).ok_or_else(corrupt)?;
+    let count = u16::from_be_bytes([count[0], count[1]]);
+    let mut entries = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let (name, mime, modified) = read_fields(&mut fields).ok_or_else(corrupt)?;
+        let size = take(&mut fields, 4).ok_or_else(corrupt)?;
+        entries.push(ArchiveEntry {
+            name,
+            mime,
+            modified,
+            size: u32::from_be_bytes([size[0], size[1], size[2], size[3]]) as usize,
+        });
+    }
+    if !fields.is_empty() {
+        return Err(corrupt());
+    }
+
+    // Entry sizes must account for exactly the archived data
+    let total: usize = entries.iter().map(|entry| entry.size).sum();
+    let stored = header
+        .compressed
+        .map_or(header.length as usize, |compressed| {
+            compressed.original_length as usize
+        });
+    if total != stored {
+        return Err(corrupt());
+    }
+    Ok(entries)
+}
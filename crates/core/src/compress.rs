@@ -0,0 +1,167 @@
+use crate::header::{Compressed, Header};
+use crate::ImgSteganoError;
+
+#[cfg(feature = "deflate")]
+const ALGORITHM_DEFLATE: u8 = 1;
+#[cfg(feature = "zstd")]
+const ALGORITHM_ZSTD: u8 = 2;
+
+/// Compression applied to a payload before it is embedded
+///
+/// Compressed payloads are decompressed automatically when decoding.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Compression {
+    /// Store the payload as is
+    #[default]
+    None,
+    /// DEFLATE (requires the `deflate` feature)
+    #[cfg(feature = "deflate")]
+    Deflate,
+    /// Zstandard (requires the `zstd` feature)
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+
+impl Compression {
+    /// Compress a payload, returning `None` if compression would not make it smaller
+    pub(crate) fn apply(
+        self,
+        payload: &[u8],
+    ) -> Result<Option<(Compressed, Vec<u8>)>, ImgSteganoError> {
+        let Some((algorithm, compressed)) = self.compress(payload)? else {
+            return Ok(None);
+        };
+
+        // Small or high-entropy payloads can grow, store those uncompressed
+        if compressed.len() + COMPRESSED_SECTION_SIZE >= payload.len() {
+            return Ok(None);
+        }
+        let section = Compressed {
+            algorithm,
+            original_length: payload.len() as u32,
+        };
+        Ok(Some((section, compressed)))
+    }
+
+    #[cfg_attr(
+        not(any(feature = "deflate", feature = "zstd")),
+        allow(unused_variables)
+    )]
+    fn compress(self, payload: &[u8]) -> Result<Option<(u8, Vec<u8>)>, ImgSteganoError> {
+        match self {
+            Compression::None => Ok(None),
+            #[cfg(feature = "deflate")]
+            Compression::Deflate => Ok(Some((ALGORITHM_DEFLATE, deflate(payload)?))),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => zstd::bulk::compress(payload, zstd::DEFAULT_COMPRESSION_LEVEL)
+                .map(|compressed| Some((ALGORITHM_ZSTD, compressed)))
+                .map_err(|_| ImgSteganoError::CompressionFailed),
+        }
+    }
+}
+
+/// Size of the header section describing a compressed payload
+pub(crate) const COMPRESSED_SECTION_SIZE: usize = 1 + 4;
+
+/// Build the header and body for a payload, compressing it if requested
+pub(crate) fn compress(
+    payload: &[u8],
+    compression: Compression,
+) -> Result<(Header, Vec<u8>), ImgSteganoError> {
+    match compression.apply(payload)? {
+        Some((section, body)) => Ok((Header::new(&body).with_compression(section), body)),
+        None => Ok((Header::new(payload), payload.to_vec())),
+    }
+}
+
+/// Reverse the compression recorded in the header, if any
+pub(crate) fn decompress(header: &Header, body: Vec<u8>) -> Result<Vec<u8>, ImgSteganoError> {
+    let Some(compressed) = header.compressed else {
+        return Ok(body);
+    };
+    let original_length = compressed.original_length as usize;
+
+    // one byte more than recorded shows up a stream that is too long
+    let payload = inflate_with(compressed.algorithm, &body, original_length + 1)?;
+    if payload.len() != original_length {
+        return Err(ImgSteganoError::DecompressionFailed);
+    }
+    Ok(payload)
+}
+
+/// The first `length` bytes of the payload, decompressing no further than
+/// needed
+///
+/// `length` must not exceed the length of the payload.
+pub(crate) fn decompress_prefix(
+    header: &Header,
+    mut body: Vec<u8>,
+    length: usize,
+) -> Result<Vec<u8>, ImgSteganoError> {
+    let Some(compressed) = header.compressed else {
+        body.truncate(length);
+        return Ok(body);
+    };
+    let payload = inflate_with(compressed.algorithm, &body, length)?;
+    if payload.len() != length {
+        return Err(ImgSteganoError::DecompressionFailed);
+    }
+    Ok(payload)
+}
+
+#[cfg_attr(
+    not(any(feature = "deflate", feature = "zstd")),
+    allow(unused_variables)
+)]
+/// Decompress at most `limit` bytes of `body`
+fn inflate_with(algorithm: u8, body: &[u8], limit: usize) -> Result<Vec<u8>, ImgSteganoError> {
+    match algorithm {
+        #[cfg(feature = "deflate")]
+        ALGORITHM_DEFLATE => inflate(body, limit),
+        #[cfg(feature = "zstd")]
+        ALGORITHM_ZSTD => unzstd(body, limit),
+        algorithm => Err(ImgSteganoError::UnsupportedCompression { algorithm }),
+    }
+}
+
+#[cfg(feature = "deflate")]
+fn deflate(payload: &[u8]) -> Result<Vec<u8>, ImgSteganoError> {
+    use std::io::Write;
+
+    let mut encoder = flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::best());
+    encoder
+        .write_all(payload)
+        .map_err(|_| ImgSteganoError::CompressionFailed)?;
+    encoder
+        .finish()
+        .map_err(|_| ImgSteganoError::CompressionFailed)
+}
+
+#[cfg(feature = "deflate")]
+fn inflate(body: &[u8], limit: usize) -> Result<Vec<u8>, ImgSteganoError> {
+    use std::io::Read;
+
+    // never read past the limit, so a corrupt header cannot make us
+    // inflate an unbounded amount of data
+    let mut payload = Vec::new();
+    flate2::read::DeflateDecoder::new(body)
+        .take(limit as u64)
+        .read_to_end(&mut payload)
+        .map_err(|_| ImgSteganoError::DecompressionFailed)?;
+    Ok(payload)
+}
+
+#[cfg(feature = "zstd")]
+fn unzstd(body: &[u8], limit: usize) -> Result<Vec<u8>, ImgSteganoError> {
+    use std::io::Read;
+
+    // as with DEFLATE, grow the output as data arrives instead of trusting
+    // the recorded length for an up-front allocation
+    let mut payload = Vec::new();
+    zstd::stream::read::Decoder::new(body)
+        .map_err(|_| ImgSteganoError::DecompressionFailed)?
+        .take(limit as u64)
+        .read_to_end(&mut payload)
+        .map_err(|_| ImgSteganoError::DecompressionFailed)?;
+    Ok(payload)
+}
@@ -1,35 +1,227 @@
 use std::path::PathBuf;
 
-use crate::Image;
-use crate::ImgSteganoError;
-use image::{GenericImageView, Pixel};
-
-pub fn decode_from_image(Image(encoded_image): &Image) -> Result<String, ImgSteganoError> {
-    let (width, height) = encoded_image.dimensions();
-    let mut decoded_bytes = Vec::new();
-    let mut current_byte = 0u8;
-    let mut bit_count = 0;
-
-    'outer: for y in 0..height {
-        for x in 0..width {
-            let pixel = encoded_image.get_pixel(x, y);
-            let rgb = pixel.to_rgb().0;
-            for &channel in &rgb {
-                let bit = channel & 1;
-                current_byte = (current_byte << 1) | bit;
-                bit_count += 1;
-                if bit_count == 8 {
-                    if current_byte == 0 {
-                        break 'outer; // End marker
-                    }
-                    decoded_bytes.push(current_byte);
-                    current_byte = 0;
-                    bit_count = 0;
-                }
-            }
+use crate::compress::decompress;
+use crate::header::{Header, FLAG_LAYOUT, HEADER_SIZE, MAGIC};
+use crate::lsb::{Layout, LAYOUT_SIZE};
+use crate::traversal::StegoKey;
+use crate::{adaptive, fec, lsb, matrix, DecodeOptions, Image, ImgSteganoError};
+use image::DynamicImage;
+
+/// Decode a byte payload previously hidden with [`encode_bytes`](crate::encode_bytes)
+///
+/// Returns [`ImgSteganoError::NoPayloadFound`] if the image does not start with
+/// a valid payload header, and [`ImgSteganoError::ChecksumMismatch`] if the
+/// payload was altered after encoding.
+pub fn decode_bytes(encoded_image: &Image) -> Result<Vec<u8>, ImgSteganoError> {
+    decode_with_report(encoded_image).map(|(payload, _)| payload)
+}
+
+/// Decode a byte payload with the given options
+///
+/// Needed for payloads embedded with a stego key, see
+/// [`DecodeOptions::with_stego_key`].
+pub fn decode_bytes_with_options(
+    encoded_image: &Image,
+    options: &DecodeOptions,
+) -> Result<Vec<u8>, ImgSteganoError> {
+    let (header, body, _) = extract_with_options(encoded_image, options)?;
+    ensure_plaintext(&header)?;
+    decompress(&header, body)
+}
+
+/// Details about how a payload was recovered
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DecodeReport {
+    /// Number of corrupted bytes repaired by error correction
+    pub corrected_errors: usize,
+}
+
+/// Decode a byte payload, also reporting how many errors were corrected
+///
+/// Payloads encoded with [`EncodeOptions::with_error_correction`](crate::EncodeOptions::with_error_correction)
+/// are repaired transparently as long as each block has no more corrupted
+/// bytes than half its parity.
+pub fn decode_with_report(
+    encoded_image: &Image,
+) -> Result<(Vec<u8>, DecodeReport), ImgSteganoError> {
+    decode_with_report_with_options(encoded_image, &DecodeOptions::default())
+}
+
+/// [`decode_with_report`] for a payload embedded with a stego key
+pub fn decode_with_report_with_options(
+    encoded_image: &Image,
+    options: &DecodeOptions,
+) -> Result<(Vec<u8>, DecodeReport), ImgSteganoError> {
+    let (header, body, report) = extract_with_options(encoded_image, options)?;
+    ensure_plaintext(&header)?;
+    Ok((decompress(&header, body)?, report))
+}
+
+/// Reject payloads whose body can only be read with a key
+pub(crate) fn ensure_plaintext(header: &Header) -> Result<(), ImgSteganoError> {
+    if header.encryption.is_some() {
+        return Err(ImgSteganoError::PasswordRequired);
+    }
+    if header.recipients.is_some() {
+        return Err(ImgSteganoError::RecipientKeyRequired);
+    }
+    Ok(())
+}
+
+/// Find the layout a stream was embedded with from its prefix
+///
+/// The prefix is always embedded with the default layout: a plain header
+/// records a non-default layout right after its fixed part, and an error
+/// correction preamble always records it. Returns the layout, the size of
+/// the prefix and whether the stream starts with a plain header (`true`)
+/// or an error correction preamble (`false`).
+pub(crate) fn read_prefix(
+    image: &DynamicImage,
+    key: Option<&StegoKey>,
+) -> Option<(Layout, usize, bool)> {
+    let lead: Vec<u8> = lsb::read_bytes(image, Layout::default(), key)
+        .take(fec::PREAMBLE_SIZE)
+        .collect();
+    let (layout, prefix_size, plain) = if lead.starts_with(&MAGIC) {
+        let flags = *lead.get(5)?;
+        if flags & FLAG_LAYOUT == 0 {
+            (Layout::default(), HEADER_SIZE, true)
+        } else {
+            let raw = lead.get(HEADER_SIZE..HEADER_SIZE + LAYOUT_SIZE)?;
+            let layout = Layout::from_bytes(raw.try_into().ok()?);
+            (layout, HEADER_SIZE + LAYOUT_SIZE, true)
         }
+    } else {
+        let preamble = fec::decode_preamble(&lead.try_into().ok()?)?;
+        (preamble.layout, fec::PREAMBLE_SIZE, false)
+    };
+    // A garbled layout could not have been written into this image
+    let valid = layout.validate(image).is_ok() && layout.normalized(image.color()) == layout;
+    valid.then_some((layout, prefix_size, plain))
+}
+
+/// Read the header and checksum-verified payload body from the image LSBs
+///
+/// The layout is read from the prefix, and error corrected streams are
+/// detected by their preamble and repaired before the header is parsed.
+pub(crate) fn extract(
+    encoded_image: &Image,
+) -> Result<(Header, Vec<u8>, DecodeReport), ImgSteganoError> {
+    extract_with_options(encoded_image, &DecodeOptions::default())
+}
+
+/// [`extract`] a payload that may have been embedded with a stego key
+pub(crate) fn extract_with_options(
+    Image(encoded_image): &Image,
+    options: &DecodeOptions,
+) -> Result<(Header, Vec<u8>, DecodeReport), ImgSteganoError> {
+    let key = options.stego_key.as_ref();
+    let (layout, prefix_size, plain) =
+        read_prefix(encoded_image, key).ok_or(ImgSteganoError::NoPayloadFound)?;
+    let raw_capacity = lsb::stream_capacity(encoded_image, layout, key, prefix_size);
+    let mut bytes = lsb::read_stream(encoded_image, layout, key, prefix_size);
+    let lead: Vec<u8> = bytes.by_ref().take(fec::PREAMBLE_SIZE).collect();
+
+    let mut report = DecodeReport::default();
+    let (mut stream, available): (Box<dyn Iterator<Item = u8>>, usize) = if plain {
+        (Box::new(lead.into_iter().chain(bytes)), raw_capacity)
+    } else {
+        let fec::Preamble {
+            parity,
+            length,
+            corrected,
+            ..
+        } = preamble(lead, raw_capacity)?;
+        let (stream, repaired) = fec::decode_stream(&mut bytes, length, parity)?;
+        report.corrected_errors = corrected + repaired;
+        (Box::new(stream.into_iter()), length)
+    };
+
+    let header = Header::read(&mut stream)?;
+    let length = header.length as usize;
+    let header_size = HEADER_SIZE + header.extension_size();
+    let mut capacity = available.saturating_sub(header_size);
+    // Adaptively placed bodies continue in the most textured slots instead
+    if let Some(coding) = header
+        .coding
+        .filter(|coding| coding.cost_map != adaptive::COST_MAP_NONE)
+    {
+        // the encoder never combines adaptive embedding with error correction
+        if !plain {
+            return Err(ImgSteganoError::UnsupportedCostMap {
+                cost_map: coding.cost_map,
+            });
+        }
+        let slots = adaptive::body_slots(
+            encoded_image,
+            layout,
+            key,
+            prefix_size,
+            header_size,
+            coding.cost_map,
+        )?;
+        capacity = slots.len() * layout.bits_per_channel as usize / 8;
+        stream = Box::new(lsb::read_slots(encoded_image, layout, slots.into_iter()));
     }
 
+    // Matrix embedded bodies take up more room than their length, reject
+    // lengths that could not have been written into this image
+    let stored_size = matrix::coded_size(length, header.coding, capacity)?;
+
+    let stored: Vec<u8> = stream.take(stored_size).collect();
+    if stored.len() < stored_size {
+        return Err(ImgSteganoError::TruncatedPayload);
+    }
+    let body = matrix::decode(stored, length, header.coding)?;
+    header.verify(&body)?;
+    Ok((header, body, report))
+}
+
+/// Read just the header of a payload, leaving the body unread
+///
+/// Error corrected streams are only corrected as far as the header reaches.
+pub(crate) fn read_header_with_options(
+    Image(encoded_image): &Image,
+    options: &DecodeOptions,
+) -> Result<Header, ImgSteganoError> {
+    let key = options.stego_key.as_ref();
+    let (layout, prefix_size, plain) =
+        read_prefix(encoded_image, key).ok_or(ImgSteganoError::NoPayloadFound)?;
+    let mut bytes = lsb::read_stream(encoded_image, layout, key, prefix_size);
+    if plain {
+        return Header::read(&mut bytes);
+    }
+
+    let raw_capacity = lsb::stream_capacity(encoded_image, layout, key, prefix_size);
+    let lead: Vec<u8> = bytes.by_ref().take(fec::PREAMBLE_SIZE).collect();
+    let fec::Preamble { parity, length, .. } = preamble(lead, raw_capacity)?;
+    let mut reader = fec::Reader::new(bytes, length, parity);
+    let header = Header::read(&mut reader);
+    match reader.error {
+        Some(error) => Err(error),
+        None => header,
+    }
+}
+
+/// Decode an error correction preamble from the `lead` of a stream
+fn preamble(lead: Vec<u8>, raw_capacity: usize) -> Result<fec::Preamble, ImgSteganoError> {
+    let lead = lead
+        .try_into()
+        .map_err(|_| ImgSteganoError::NoPayloadFound)?;
+    let preamble = fec::decode_preamble(&lead).ok_or(ImgSteganoError::NoPayloadFound)?;
+
+    // Reject lengths that could not have been written into this image
+    if fec::encoded_size(preamble.length, preamble.parity) > raw_capacity {
+        return Err(ImgSteganoError::InvalidLength {
+            length: preamble.length,
+            available: raw_capacity,
+        });
+    }
+    Ok(preamble)
+}
+
+pub fn decode_from_image(encoded_image: &Image) -> Result<String, ImgSteganoError> {
+    let decoded_bytes = decode_bytes(encoded_image)?;
     // Return proper error for invalid UTF-8 instead of lossy conversion
     String::from_utf8(decoded_bytes).map_err(|_| ImgSteganoError::InvalidUtf8)
 }
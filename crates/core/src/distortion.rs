@@ -0,0 +1,217 @@
+use std::fmt;
+use std::sync::Arc;
+
+use image::{DynamicImage, GenericImageView};
+
+use crate::lsb::{Layout, Samples};
+use crate::ImgSteganoError;
+
+/// Cost of changing each sample of an image, in interleaved order
+pub type CostFunction = dyn Fn(&DynamicImage) -> Vec<f32> + Send + Sync;
+
+/// Cost function deciding which samples syndrome-trellis coding changes
+///
+/// Only the encoder evaluates costs, so decoding works the same whichever
+/// was used.
+#[derive(Clone, Default)]
+pub enum Distortion {
+    /// Every change costs the same, which minimizes the number of changes
+    #[default]
+    Uniform,
+    /// HILL: changes are cheap where a high-pass residual is strong around
+    /// the sample, i.e. in noisy texture that is hard to model
+    Hill,
+    /// Changes are cheap where the local 3x3 variance is high, the texture
+    /// measure behind adaptive embedding
+    Adaptive,
+    /// Costs from a caller supplied function, see [`Distortion::custom`]
+    Custom(Arc<CostFunction>),
+}
+
+impl Distortion {
+    /// Use `cost` to rate changes
+    ///
+    /// It gets the image as embedded into and returns a finite, non-negative
+    /// cost for every sample, pixel by pixel and channel by channel within each
+    /// pixel, alpha included.
+    pub fn custom(cost: impl Fn(&DynamicImage) -> Vec<f32> + Send + Sync + 'static) -> Self {
+        Distortion::Custom(Arc::new(cost))
+    }
+}
+
+impl fmt::Debug for Distortion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Distortion::Uniform => f.write_str("Uniform"),
+            Distortion::Hill => f.write_str("Hill"),
+            Distortion::Adaptive => f.write_str("Adaptive"),
+            Distortion::Custom(_) => f.write_str("Custom(..)"),
+        }
+    }
+}
+
+/// Size of the low-pass window spreading HILL costs over their neighbourhood
+const HILL_SPREAD: usize = 15;
+
+/// Cost of flipping each of the cover bits carried by `slots`
+///
+/// The cost of a sample is scaled by the weight of the payload bit within
+/// it, so flipping a higher bit costs more. The first `skip` bits are left
+/// out and at most `count` bits are returned.
+pub(crate) fn bit_costs(
+    image: &DynamicImage,
+    layout: Layout,
+    slots: impl Iterator<Item = usize>,
+    skip: usize,
+    count: usize,
+    distortion: &Distortion,
+) -> Result<Vec<f32>, ImgSteganoError> {
+    let sample_costs = sample_costs(image, distortion)?;
+    let bits_per_channel = layout.bits_per_channel;
+    Ok(slots
+        .flat_map(|index| {
+            let cost = sample_costs[index];
+            (0..bits_per_channel)
+                .rev()
+                .map(move |shift| cost * (1u32 << shift) as f32)
+        })
+        .skip(skip)
+        .take(count)
+        .collect())
+}
+
+/// Cost of changing every interleaved sample of the image
+fn sample_costs(
+    image: &DynamicImage,
+    distortion: &Distortion,
+) -> Result<Vec<f32>, ImgSteganoError> {
+    let samples = Samples::new(image);
+    let (width, height) = image.dimensions();
+    let (width, height) = (width as usize, height as usize);
+    let samples_per_pixel = image.color().channel_count() as usize;
+
+    let mut costs = vec![1.0; width * height * samples_per_pixel];
+    match distortion {
+        Distortion::Uniform => return Ok(costs),
+        Distortion::Custom(cost) => {
+            let custom = cost(image);
+            if custom.len() != costs.len() {
+                return Err(ImgSteganoError::InvalidOptions {
+                    reason: "custom distortion must return one cost per sample",
+                });
+            }
+            if !custom.iter().all(|cost| cost.is_finite() && *cost >= 0.0) {
+                return Err(ImgSteganoError::InvalidOptions {
+                    reason: "custom distortion costs must be finite and not negative",
+                });
+            }
+            return Ok(custom);
+        }
+        Distortion::Hill | Distortion::Adaptive => {}
+    }
+    for channel in 0..samples_per_pixel {
+        let plane = Plane {
+            values: (0..width * height)
+                .map(|pixel| samples.get(pixel * samples_per_pixel + channel) as f32)
+                .collect(),
+            width,
+            height,
+        };
+        let plane_costs = match distortion {
+            Distortion::Hill => plane.hill(),
+            Distortion::Adaptive => plane.inverse_variance(),
+            Distortion::Uniform | Distortion::Custom(_) => {
+                unreachable!("uniform and custom costs are returned above")
+            }
+        };
+        for (pixel, cost) in plane_costs.into_iter().enumerate() {
+            costs[pixel * samples_per_pixel + channel] = cost;
+        }
+    }
+    Ok(costs)
+}
+
+/// One color plane of an image
+struct Plane {
+    values: Vec<f32>,
+    width: usize,
+    height: usize,
+}
+
+impl Plane {
+    fn at(&self, x: isize, y: isize) -> f32 {
+        // samples beyond the border repeat the edge
+        let x = x.clamp(0, self.width as isize - 1) as usize;
+        let y = y.clamp(0, self.height as isize - 1) as usize;
+        self.values[y * self.width + x]
+    }
+
+    fn with_values(&self, values: Vec<f32>) -> Plane {
+        Plane {
+            values,
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    /// HILL costs: KB high-pass residual, 3x3 mean of its magnitude,
+    /// reciprocal, then a wide mean to spread low costs over textured areas
+    fn hill(&self) -> Vec<f32> {
+        const KB: [[f32; 3]; 3] = [[-1.0, 2.0, -1.0], [2.0, -4.0, 2.0], [-1.0, 2.0, -1.0]];
+        let mut residual = Vec::with_capacity(self.values.len());
+        for y in 0..self.height as isize {
+            for x in 0..self.width as isize {
+                let mut sum = 0.0;
+                for (dy, row) in KB.iter().enumerate() {
+                    for (dx, weight) in row.iter().enumerate() {
+                        sum += weight * self.at(x + dx as isize - 1, y + dy as isize - 1);
+                    }
+                }
+                residual.push((sum / 4.0).abs());
+            }
+        }
+        let activity = self.with_values(residual).mean(3);
+        let reciprocal = activity.iter().map(|value| 1.0 / (value + 1e-3)).collect();
+        self.with_values(reciprocal).mean(HILL_SPREAD)
+    }
+
+    /// Reciprocal of the variance of each 3x3 neighbourhood, plus one
+    fn inverse_variance(&self) -> Vec<f32> {
+        let squares = self.with_values(self.values.iter().map(|value| value * value).collect());
+        self.mean(3)
+            .into_iter()
+            .zip(squares.mean(3))
+            .map(|(mean, square)| 1.0 / (1.0 + (square - mean * mean).max(0.0)))
+            .collect()
+    }
+
+    /// Mean of each `size x size` window, leaving out samples beyond the border
+    fn mean(&self, size: usize) -> Vec<f32> {
+        // summed area table with a zero row and column in front
+        let stride = self.width + 1;
+        let mut sums = vec![0.0f64; stride * (self.height + 1)];
+        for y in 0..self.height {
+            for x in 0..self.width {
+                sums[(y + 1) * stride + x + 1] = self.values[y * self.width + x] as f64
+                    + sums[y * stride + x + 1]
+                    + sums[(y + 1) * stride + x]
+                    - sums[y * stride + x];
+            }
+        }
+
+        let radius = size / 2;
+        let mut means = Vec::with_capacity(self.values.len());
+        for y in 0..self.height {
+            let (top, bottom) = (y.saturating_sub(radius), (y + radius + 1).min(self.height));
+            for x in 0..self.width {
+                let (left, right) = (x.saturating_sub(radius), (x + radius + 1).min(self.width));
+                let sum = sums[bottom * stride + right]
+                    - sums[top * stride + right]
+                    - sums[bottom * stride + left]
+                    + sums[top * stride + left];
+                means.push((sum / ((bottom - top) * (right - left)) as f64) as f32);
+            }
+        }
+        means
+    }
+}
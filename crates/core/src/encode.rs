@@ -1,58 +1,254 @@
+use std::borrow::Cow;
 use std::{io::Cursor, path::PathBuf};
 
-use image::ImageFormat;
-use image::{GenericImage, GenericImageView, Pixel, Rgb};
+use image::{DynamicImage, ImageFormat};
 
-use crate::{calculate_capacity, Image, ImgSteganoError};
+use crate::compress::compress;
+use crate::header::{Coding, Header, Signature, HEADER_SIZE};
+use crate::{
+    adaptive, distortion, fec, lsb, matrix, trellis, EncodeOptions, Image, ImgSteganoError,
+};
 
-pub fn encode_from_image(
+/// Encode an arbitrary byte payload into an image
+///
+/// The payload is prefixed with a versioned header carrying its length, so it
+/// may contain any byte value including `0x00`.
+pub fn encode_bytes(input_image: Image, payload: &[u8]) -> Result<Image, ImgSteganoError> {
+    encode_bytes_with_options(input_image, payload, &EncodeOptions::default())
+}
+
+/// Encode an arbitrary byte payload into an image with the given options
+pub fn encode_bytes_with_options(
     Image(input_image): Image,
-    message: &str,
+    payload: &[u8],
+    options: &EncodeOptions,
 ) -> Result<Image, ImgSteganoError> {
-    // Validate message is not empty
-    if message.is_empty() {
+    // Validate payload is not empty
+    if payload.is_empty() {
         return Err(ImgSteganoError::EmptyMessage);
     }
 
-    let (width, height) = input_image.dimensions();
-    let capacity = calculate_capacity(width, height);
-    let message_bytes = message.as_bytes();
+    let (header, body) = compress(payload, options.compression)?;
+    embed(input_image, header, &body, options)
+}
 
-    // Validate message fits in image
-    if message_bytes.len() > capacity {
-        return Err(ImgSteganoError::MessageTooLarge {
-            required: message_bytes.len(),
-            available: capacity,
+/// Signs the final header and the body, see [`embed_signed`]
+pub(crate) type Sign<'a> = &'a dyn Fn(&Header, &[u8]) -> Signature;
+
+/// Write a header followed by the payload body into the image LSBs
+pub(crate) fn embed(
+    image: DynamicImage,
+    header: Header,
+    body: &[u8],
+    options: &EncodeOptions,
+) -> Result<Image, ImgSteganoError> {
+    embed_with(image, header, body, options, None)
+}
+
+/// [`embed`] a payload whose header carries a signature section
+///
+/// `sign` replaces the signature once every other section is final, such
+/// as the code chosen for the body.
+#[cfg_attr(not(feature = "sign"), allow(dead_code))]
+pub(crate) fn embed_signed(
+    image: DynamicImage,
+    header: Header,
+    body: &[u8],
+    options: &EncodeOptions,
+    sign: Sign,
+) -> Result<Image, ImgSteganoError> {
+    embed_with(image, header, body, options, Some(sign))
+}
+
+fn embed_with(
+    mut image: DynamicImage,
+    header: Header,
+    body: &[u8],
+    options: &EncodeOptions,
+    sign: Option<Sign>,
+) -> Result<Image, ImgSteganoError> {
+    lsb::normalize(&mut image);
+    options.layout.validate(&image)?;
+    let layout = options.layout.normalized(image.color());
+
+    let key = options.stego_key.as_ref();
+    let mut header = header.with_layout(layout);
+    let prefix_size = if options.error_correction > 0 {
+        fec::PREAMBLE_SIZE
+    } else {
+        header.prefix_size()
+    };
+    let raw_capacity = lsb::stream_capacity(&image, layout, key, prefix_size);
+    let coded = options.matrix_embedding || options.trellis.is_some();
+    if (coded || options.adaptive) && options.error_correction > 0 {
+        return Err(ImgSteganoError::InvalidOptions {
+            reason:
+                "matrix, trellis and adaptive embedding cannot be combined with error correction",
         });
     }
+    if options.matrix_embedding && options.trellis.is_some() {
+        return Err(ImgSteganoError::InvalidOptions {
+            reason: "matrix embedding cannot be combined with syndrome-trellis coding",
+        });
+    }
+    if options.adaptive && options.lsb_matching {
+        return Err(ImgSteganoError::InvalidOptions {
+            reason: "adaptive embedding cannot be combined with LSB matching",
+        });
+    }
+    if let Some(coding) = coding(options) {
+        header = header.with_coding(coding);
+    }
 
-    let mut output_image = input_image.clone();
-    let mut message_bits = message_bytes
-        .iter()
-        .flat_map(|byte| (0..8).rev().map(move |i| (byte >> i) & 1))
-        .collect::<Vec<u8>>();
-    // adding message termination, to mark the end of a message
-    message_bits.extend(vec![0; 8]);
-    let mut bit_index = 0;
-
-    'outer: for y in 0..height {
-        for x in 0..width {
-            let pixel = output_image.get_pixel(x, y);
-            let mut rgb = pixel.to_rgb().0;
-            for channel in &mut rgb {
-                if bit_index < message_bits.len() {
-                    // clear the last bit with OxFE as the bitmask
-                    // set the message_bits[i] at the cleared LSB
-                    *channel = (*channel & 0xFE) | message_bits[bit_index];
-                    bit_index += 1;
-                } else {
-                    break 'outer;
-                }
-            }
-            output_image.put_pixel(x, y, Rgb(rgb).to_rgba());
+    let header_size = HEADER_SIZE + header.extension_size();
+    let adaptive_slots = match header.coding {
+        Some(coding) if coding.cost_map != adaptive::COST_MAP_NONE => Some(adaptive::body_slots(
+            &image,
+            layout,
+            key,
+            prefix_size,
+            header_size,
+            coding.cost_map,
+        )?),
+        _ => None,
+    };
+    let available = match &adaptive_slots {
+        Some(slots) => slots.len() * layout.bits_per_channel as usize / 8,
+        None => raw_capacity.saturating_sub(header_size),
+    };
+    let too_large = |size: usize| ImgSteganoError::MessageTooLarge {
+        required: size,
+        available: raw_capacity.saturating_sub(header_size),
+    };
+
+    // the code is chosen against the bits about to be replaced
+    let cover_slots = || -> Box<dyn Iterator<Item = usize>> {
+        match &adaptive_slots {
+            Some(slots) => Box::new(slots.iter().copied()),
+            None => Box::new(lsb::stream_slots(&image, layout, key, prefix_size)),
+        }
+    };
+    let read_cover = |size: usize| -> Vec<u8> {
+        match &adaptive_slots {
+            Some(_) => lsb::read_slots(&image, layout, cover_slots())
+                .take(size)
+                .collect(),
+            None => lsb::read_stream(&image, layout, key, prefix_size)
+                .skip(header_size)
+                .take(size)
+                .collect(),
+        }
+    };
+    let payload = body;
+    let body = match &mut header.coding {
+        Some(coding) if coding.scheme == matrix::SCHEME_HAMMING => {
+            let k = matrix::choose(body.len(), available * 8).ok_or(too_large(body.len()))?;
+            coding.parameter = k;
+            let mut cover = read_cover(matrix::coded_size(body.len(), Some(*coding), available)?);
+            matrix::embed(&mut cover, body, k);
+            Cow::Owned(cover)
+        }
+        Some(coding) if coding.scheme == trellis::SCHEME_TRELLIS => {
+            let width = trellis::choose(body.len(), available).ok_or(too_large(body.len()))?;
+            coding.parameter = width;
+            let mut cover = read_cover(matrix::coded_size(body.len(), Some(*coding), available)?);
+            // the rest of the header precedes the body unless it has slots
+            // of its own
+            let skip = if adaptive_slots.is_some() {
+                0
+            } else {
+                (header_size - prefix_size) * 8
+            };
+            let distortion = options.trellis.clone().unwrap_or_default();
+            let costs = distortion::bit_costs(
+                &image,
+                layout,
+                cover_slots(),
+                skip,
+                cover.len() * 8,
+                &distortion,
+            )?;
+            trellis::embed(&mut cover, body, width, &costs)?;
+            Cow::Owned(cover)
+        }
+        _ => Cow::Borrowed(body),
+    };
+    if let Some(sign) = sign {
+        header.signature = Some(sign(&header, payload));
+    }
+
+    if let Some(slots) = adaptive_slots {
+        if body.len() > available {
+            return Err(too_large(body.len()));
+        }
+        lsb::write_stream(
+            &mut image,
+            layout,
+            key,
+            false,
+            prefix_size,
+            &header.to_bytes(),
+        );
+        lsb::write_slots(&mut image, layout, slots.into_iter(), false, 0, &body);
+        return Ok(image.into());
+    }
+
+    // header followed by the payload itself
+    let mut stream = header.to_bytes();
+    stream.extend_from_slice(&body);
+    if options.error_correction > 0 {
+        if options.error_correction as usize >= fec::BLOCK_SIZE {
+            return Err(ImgSteganoError::InvalidOptions {
+                reason: "error correction parity must be below 255 bytes per block",
+            });
         }
+        stream = fec::encode_stream(&stream, options.error_correction, layout);
     }
-    Ok(output_image.into())
+
+    // Validate payload fits in image
+    if stream.len() > raw_capacity {
+        return Err(ImgSteganoError::MessageTooLarge {
+            required: stream.len() - header_size,
+            available: raw_capacity.saturating_sub(header_size),
+        });
+    }
+
+    lsb::write_stream(
+        &mut image,
+        layout,
+        key,
+        options.lsb_matching,
+        prefix_size,
+        &stream,
+    );
+    Ok(image.into())
+}
+
+/// Coding section recorded for `options`, if any
+///
+/// The parameter is left at `0`, it is filled in once the room left for the
+/// body is known.
+pub(crate) fn coding(options: &EncodeOptions) -> Option<Coding> {
+    let coded = options.matrix_embedding || options.trellis.is_some();
+    (coded || options.adaptive).then(|| Coding {
+        scheme: if options.matrix_embedding {
+            matrix::SCHEME_HAMMING
+        } else if options.trellis.is_some() {
+            trellis::SCHEME_TRELLIS
+        } else {
+            matrix::SCHEME_NONE
+        },
+        parameter: 0,
+        cost_map: if options.adaptive {
+            adaptive::COST_MAP_VARIANCE
+        } else {
+            adaptive::COST_MAP_NONE
+        },
+    })
+}
+
+pub fn encode_from_image(input_image: Image, message: &str) -> Result<Image, ImgSteganoError> {
+    encode_bytes(input_image, message.as_bytes())
 }
 
 pub fn encode_from_u8_array(input_image: &[u8], message: &str) -> Result<Vec<u8>, ImgSteganoError> {
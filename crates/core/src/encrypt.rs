@@ -0,0 +1,69 @@
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+
+use crate::compress::decompress;
+use crate::decode::extract;
+use crate::encode::embed;
+use crate::header::{Encryption, Header, SALT_SIZE};
+use crate::{EncodeOptions, Image, ImgSteganoError};
+
+/// Encrypt a payload with a password and encode it into an image
+///
+/// The key is derived from the password with Argon2id using a random salt,
+/// and the payload is sealed with ChaCha20-Poly1305. The salt and nonce are
+/// stored in the payload header so only the password is needed to decode.
+pub fn encode_encrypted(
+    Image(input_image): Image,
+    payload: &[u8],
+    password: &str,
+) -> Result<Image, ImgSteganoError> {
+    // Validate payload is not empty
+    if payload.is_empty() {
+        return Err(ImgSteganoError::EmptyMessage);
+    }
+
+    let mut salt = [0u8; SALT_SIZE];
+    OsRng.fill_bytes(&mut salt);
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+
+    let cipher = ChaCha20Poly1305::new(&derive_key(password, &salt)?);
+    let ciphertext = cipher
+        .encrypt(&nonce, payload)
+        .map_err(|_| ImgSteganoError::EncryptionFailed)?;
+
+    let header = Header::new(&ciphertext).with_encryption(Encryption {
+        salt,
+        nonce: nonce.into(),
+    });
+    embed(input_image, header, &ciphertext, &EncodeOptions::default())
+}
+
+/// Decode and decrypt a payload hidden with [`encode_encrypted`]
+///
+/// Returns [`ImgSteganoError::WrongPasswordOrCorrupt`] if the password is
+/// wrong or the ciphertext fails authentication.
+pub fn decode_encrypted(encoded_image: &Image, password: &str) -> Result<Vec<u8>, ImgSteganoError> {
+    let (header, ciphertext, _) = extract(encoded_image)?;
+    if header.recipients.is_some() {
+        return Err(ImgSteganoError::RecipientKeyRequired);
+    }
+    let Some(encryption) = header.encryption else {
+        return Err(ImgSteganoError::WrongPasswordOrCorrupt);
+    };
+
+    let cipher = ChaCha20Poly1305::new(&derive_key(password, &encryption.salt)?);
+    let plaintext = cipher
+        .decrypt(Nonce::from_slice(&encryption.nonce), ciphertext.as_slice())
+        .map_err(|_| ImgSteganoError::WrongPasswordOrCorrupt)?;
+    decompress(&header, plaintext)
+}
+
+fn derive_key(password: &str, salt: &[u8]) -> Result<Key, ImgSteganoError> {
+    let mut key = Key::default();
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, Params::default())
+        .hash_password_into(password.as_bytes(), salt, &mut key)
+        .map_err(|_| ImgSteganoError::EncryptionFailed)?;
+    Ok(key)
+}
@@ -10,6 +10,80 @@ pub enum ImgSteganoError {
     MessageTooLarge { required: usize, available: usize },
     #[error("Empty message provided")]
     EmptyMessage,
+    #[error("No hidden payload found in image")]
+    NoPayloadFound,
+    #[error("Unsupported payload format version: {version}")]
+    UnsupportedVersion { version: u8 },
+    #[error("Unsupported payload flags: {flags:#010b}")]
+    UnsupportedFlags { flags: u8 },
+    #[error("Encoded payload length {length} exceeds image capacity of {available} bytes")]
+    InvalidLength { length: usize, available: usize },
+    #[error("Too many errors to correct in hidden payload")]
+    UncorrectableErrors,
+    #[error("Invalid encode options: {reason}")]
+    InvalidOptions { reason: &'static str },
+    #[error("Hidden payload is truncated")]
+    TruncatedPayload,
+    #[error("Payload checksum mismatch: expected {expected:#010x}, got {actual:#010x}")]
+    ChecksumMismatch { expected: u32, actual: u32 },
+    #[error("Payload is encrypted, a password is required to decode it")]
+    PasswordRequired,
+    #[error("Payload is encrypted to recipients, a secret key is required to decode it")]
+    RecipientKeyRequired,
+    #[error("The provided key is not among the payload recipients")]
+    NotARecipient,
+    #[error("Between 1 and 255 recipients are required, got {count}")]
+    InvalidRecipientCount { count: usize },
+    #[error("Payload is not signed")]
+    SignatureMissing,
+    #[error("Payload signature is invalid")]
+    InvalidSignature,
+    #[error("Payload was signed by an untrusted key")]
+    UntrustedSigner,
+    #[error("Invalid public key")]
+    InvalidPublicKey,
+    #[error("Wrong password or corrupted payload")]
+    WrongPasswordOrCorrupt,
+    #[error("Failed to encrypt payload")]
+    EncryptionFailed,
+    #[error("Failed to compress payload")]
+    CompressionFailed,
+    #[error("Failed to decompress payload")]
+    DecompressionFailed,
+    #[error("Failed to embed payload with syndrome-trellis coding")]
+    EmbeddingFailed,
+    #[error("Unsupported compression algorithm: {algorithm}")]
+    UnsupportedCompression { algorithm: u8 },
+    #[error("Unsupported embedding code: scheme {scheme}, parameter {parameter}")]
+    UnsupportedCoding { scheme: u8, parameter: u8 },
+    #[error("Unsupported adaptive cost map: {cost_map}")]
+    UnsupportedCostMap { cost_map: u8 },
+    #[error("Payload is not a file")]
+    NotAFile,
+    #[error("Payload is not an archive")]
+    NotAnArchive,
+    #[error("Archive has no entry named {name:?}")]
+    EntryNotFound { name: String },
+    #[error("Payload is not a shard of a split payload")]
+    NotAShard,
+    #[error("Invalid shard: {reason}")]
+    InvalidShard { reason: &'static str },
+    #[error("Images belong to different split payloads")]
+    ShardSetMismatch,
+    #[error("Missing shards {missing:?} of {count}")]
+    MissingShards { missing: Vec<u16>, count: u16 },
+    #[error("Payload is not a secret share")]
+    NotAShare,
+    #[error("Invalid secret share: {reason}")]
+    InvalidShare { reason: &'static str },
+    #[error("Images belong to different secret sharings")]
+    ShareSetMismatch,
+    #[error("Only {available} shares available, {threshold} needed")]
+    InsufficientShares { available: usize, threshold: u8 },
+    #[error("Invalid file metadata: {reason}")]
+    InvalidFileMetadata { reason: &'static str },
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
     #[error("Decoded message contains invalid UTF-8")]
     InvalidUtf8,
     #[error("Lossy format detected: {format:?}. Steganography works best with lossless formats like PNG")]
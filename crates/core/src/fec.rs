@@ -0,0 +1,278 @@
+//! Reed-Solomon forward error correction over GF(256)
+
+use crate::gf256::{self, alpha, poly};
+use crate::lsb::{Layout, LAYOUT_SIZE};
+use crate::ImgSteganoError;
+
+/// Maximum length of a codeword, data and parity combined
+pub(crate) const BLOCK_SIZE: usize = 255;
+
+/// Marker starting an error corrected stream
+pub(crate) const FEC_MAGIC: [u8; 4] = *b"ISTF";
+
+/// Parity bytes protecting the frame preamble itself
+const PREAMBLE_PARITY: u8 = 8;
+
+/// Preamble data: magic, parity per block, length of the protected stream
+/// and the layout it is embedded with
+const PREAMBLE_DATA_SIZE: usize = FEC_MAGIC.len() + 1 + 4 + LAYOUT_SIZE;
+
+/// Size of the encoded preamble in the image
+pub(crate) const PREAMBLE_SIZE: usize = PREAMBLE_DATA_SIZE + PREAMBLE_PARITY as usize;
+
+fn generator(parity: u8) -> Vec<u8> {
+    (0..parity as i32).fold(vec![1], |g, i| poly::mul_poly(&g, &[1, alpha(i)]))
+}
+
+/// Systematically encode one block, appending `parity` bytes to `data`
+pub(crate) fn encode_block(data: &[u8], parity: u8) -> Vec<u8> {
+    let generator = generator(parity);
+    let mut padded = data.to_vec();
+    padded.resize(data.len() + parity as usize, 0);
+    let remainder = poly::rem(&padded, &generator);
+    let mut block = data.to_vec();
+    block.extend_from_slice(&remainder);
+    block
+}
+
+/// Correct a block in place, returning the number of bytes that were repaired
+pub(crate) fn decode_block(block: &mut [u8], parity: u8) -> Result<usize, ImgSteganoError> {
+    let syndromes = syndromes(block, parity);
+    if syndromes.iter().all(|&s| s == 0) {
+        return Ok(0);
+    }
+
+    let locator = error_locator(&syndromes, parity)?;
+    let positions = error_positions(&locator, block.len())?;
+    correct_errata(block, &syndromes, &positions);
+
+    if syndromes_of(block, parity).iter().any(|&s| s != 0) {
+        return Err(ImgSteganoError::UncorrectableErrors);
+    }
+    Ok(positions.len())
+}
+
+/// Number of bytes a stream of `length` bytes occupies once split into blocks
+pub(crate) fn encoded_size(length: usize, parity: u8) -> usize {
+    let data_per_block = BLOCK_SIZE - parity as usize;
+    PREAMBLE_SIZE + length + length.div_ceil(data_per_block) * parity as usize
+}
+
+/// Protect a stream: an encoded preamble followed by the stream split into blocks
+pub(crate) fn encode_stream(stream: &[u8], parity: u8, layout: Layout) -> Vec<u8> {
+    let mut preamble = Vec::with_capacity(PREAMBLE_DATA_SIZE);
+    preamble.extend_from_slice(&FEC_MAGIC);
+    preamble.push(parity);
+    preamble.extend_from_slice(&(stream.len() as u32).to_be_bytes());
+    preamble.extend_from_slice(&layout.to_bytes());
+
+    let mut encoded = encode_block(&preamble, PREAMBLE_PARITY);
+    for chunk in stream.chunks(BLOCK_SIZE - parity as usize) {
+        encoded.extend(encode_block(chunk, parity));
+    }
+    encoded
+}
+
+/// Decoded frame preamble
+pub(crate) struct Preamble {
+    pub parity: u8,
+    /// Length of the protected stream
+    pub length: usize,
+    pub layout: Layout,
+    /// Number of preamble bytes that were repaired
+    pub corrected: usize,
+}
+
+/// Decode the frame preamble
+///
+/// Returns `None` if the bytes are not a (correctable) preamble.
+pub(crate) fn decode_preamble(raw: &[u8; PREAMBLE_SIZE]) -> Option<Preamble> {
+    let mut block = *raw;
+    let corrected = decode_block(&mut block, PREAMBLE_PARITY).ok()?;
+    if block[..4] != FEC_MAGIC {
+        return None;
+    }
+    let parity = block[4];
+    if parity == 0 || parity as usize >= BLOCK_SIZE {
+        return None;
+    }
+    Some(Preamble {
+        parity,
+        length: u32::from_be_bytes([block[5], block[6], block[7], block[8]]) as usize,
+        layout: Layout::from_bytes([block[9], block[10]]),
+        corrected,
+    })
+}
+
+/// Read and correct `length` stream bytes protected with `parity` bytes per block
+pub(crate) fn decode_stream<I: Iterator<Item = u8>>(
+    bytes: &mut I,
+    length: usize,
+    parity: u8,
+) -> Result<(Vec<u8>, usize), ImgSteganoError> {
+    let mut reader = Reader::new(bytes, length, parity);
+    let stream: Vec<u8> = reader.by_ref().collect();
+    match reader.error {
+        Some(error) => Err(error),
+        None => Ok((stream, reader.corrected)),
+    }
+}
+
+/// Corrected stream bytes, read one block at a time as they are needed
+///
+/// Iteration stops early on a truncated or uncorrectable block, which is
+/// then kept in `error`.
+pub(crate) struct Reader<I> {
+    bytes: I,
+    remaining: usize,
+    parity: u8,
+    block: std::vec::IntoIter<u8>,
+    /// Number of bytes repaired so far
+    pub corrected: usize,
+    pub error: Option<ImgSteganoError>,
+}
+
+impl<I: Iterator<Item = u8>> Reader<I> {
+    /// Read `length` stream bytes protected with `parity` bytes per block
+    pub fn new(bytes: I, length: usize, parity: u8) -> Self {
+        Reader {
+            bytes,
+            remaining: length,
+            parity,
+            block: Vec::new().into_iter(),
+            corrected: 0,
+            error: None,
+        }
+    }
+}
+
+impl<I: Iterator<Item = u8>> Iterator for Reader<I> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        if let Some(byte) = self.block.next() {
+            return Some(byte);
+        }
+        if self.remaining == 0 || self.error.is_some() {
+            return None;
+        }
+        let data_len = (BLOCK_SIZE - self.parity as usize).min(self.remaining);
+        let mut block: Vec<u8> = self
+            .bytes
+            .by_ref()
+            .take(data_len + self.parity as usize)
+            .collect();
+        if block.len() < data_len + self.parity as usize {
+            self.error = Some(ImgSteganoError::TruncatedPayload);
+            return None;
+        }
+        match decode_block(&mut block, self.parity) {
+            Ok(repaired) => self.corrected += repaired,
+            Err(error) => {
+                self.error = Some(error);
+                return None;
+            }
+        }
+        self.remaining -= data_len;
+        block.truncate(data_len);
+        self.block = block.into_iter();
+        self.block.next()
+    }
+}
+
+fn syndromes_of(block: &[u8], parity: u8) -> Vec<u8> {
+    (0..parity as i32)
+        .map(|i| poly::eval(block, alpha(i)))
+        .collect()
+}
+
+/// Syndromes with a leading zero, which the locator and evaluator steps expect
+fn syndromes(block: &[u8], parity: u8) -> Vec<u8> {
+    let mut syndromes = vec![0];
+    syndromes.extend(syndromes_of(block, parity));
+    syndromes
+}
+
+/// Berlekamp-Massey search for the error locator polynomial
+fn error_locator(syndromes: &[u8], parity: u8) -> Result<Vec<u8>, ImgSteganoError> {
+    let mut locator = vec![1u8];
+    let mut old_locator = vec![1u8];
+    let shift = syndromes.len() - parity as usize;
+
+    for i in 0..parity as usize {
+        let k = i + shift;
+        let mut delta = syndromes[k];
+        for j in 1..locator.len() {
+            delta ^= gf256::mul(locator[locator.len() - 1 - j], syndromes[k - j]);
+        }
+        old_locator.push(0);
+        if delta != 0 {
+            if old_locator.len() > locator.len() {
+                let new_locator = poly::scale(&old_locator, delta);
+                old_locator = poly::scale(&locator, gf256::inverse(delta));
+                locator = new_locator;
+            }
+            locator = poly::add(&locator, &poly::scale(&old_locator, delta));
+        }
+    }
+
+    let leading_zeros = locator.iter().take_while(|&&c| c == 0).count();
+    locator.drain(..leading_zeros);
+    let errors = locator.len() - 1;
+    if errors * 2 > parity as usize {
+        return Err(ImgSteganoError::UncorrectableErrors);
+    }
+    Ok(locator)
+}
+
+/// Chien search for the positions of the errors in a block of `length` bytes
+fn error_positions(locator: &[u8], length: usize) -> Result<Vec<usize>, ImgSteganoError> {
+    let reversed: Vec<u8> = locator.iter().rev().copied().collect();
+    let errors = locator.len() - 1;
+    let positions: Vec<usize> = (0..length)
+        .filter(|&i| poly::eval(&reversed, alpha(i as i32)) == 0)
+        .map(|i| length - 1 - i)
+        .collect();
+    if positions.len() != errors {
+        return Err(ImgSteganoError::UncorrectableErrors);
+    }
+    Ok(positions)
+}
+
+/// Forney algorithm: compute the error magnitudes and apply them to the block
+fn correct_errata(block: &mut [u8], syndromes: &[u8], positions: &[usize]) {
+    let coefficient_positions: Vec<i32> = positions
+        .iter()
+        .map(|&p| (block.len() - 1 - p) as i32)
+        .collect();
+
+    let locator = coefficient_positions.iter().fold(vec![1u8], |loc, &i| {
+        poly::mul_poly(&loc, &poly::add(&[1], &[alpha(i), 0]))
+    });
+
+    let reversed_syndromes: Vec<u8> = syndromes.iter().rev().copied().collect();
+    let mut divisor = vec![0u8; locator.len() + 1];
+    divisor[0] = 1;
+    let mut evaluator = poly::rem(&poly::mul_poly(&reversed_syndromes, &locator), &divisor);
+    evaluator.reverse();
+
+    let xs: Vec<u8> = coefficient_positions
+        .iter()
+        .map(|&p| alpha(-(BLOCK_SIZE as i32 - p)))
+        .collect();
+
+    for (i, &xi) in xs.iter().enumerate() {
+        let xi_inverse = gf256::inverse(xi);
+        let locator_derivative = xs
+            .iter()
+            .enumerate()
+            .filter(|&(j, _)| j != i)
+            .fold(1u8, |acc, (_, &xj)| {
+                gf256::mul(acc, 1 ^ gf256::mul(xi_inverse, xj))
+            });
+
+        let reversed_evaluator: Vec<u8> = evaluator.iter().rev().copied().collect();
+        let y = gf256::mul(xi, poly::eval(&reversed_evaluator, xi_inverse));
+        block[positions[i]] ^= gf256::div(y, locator_derivative);
+    }
+}
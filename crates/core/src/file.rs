@@ -0,0 +1,229 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::compress::{compress, decompress};
+use crate::decode::{ensure_plaintext, extract_with_options};
+use crate::encode::embed;
+use crate::header::Metadata;
+use crate::{DecodeOptions, EncodeOptions, Image, ImgSteganoError};
+
+/// Metadata kind of a single embedded file
+pub(crate) const KIND_FILE: u8 = 1;
+
+/// MIME type used when none is known
+pub const DEFAULT_MIME: &str = "application/octet-stream";
+
+/// A file hidden in an image together with its metadata
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HiddenFile {
+    /// File name without any directory components
+    pub name: String,
+    pub mime: String,
+    pub modified: Option<SystemTime>,
+    pub data: Vec<u8>,
+}
+
+impl HiddenFile {
+    pub fn new<N: Into<String>>(name: N, data: Vec<u8>) -> Self {
+        let name = name.into();
+        HiddenFile {
+            mime: mime_from_name(&name).to_string(),
+            name,
+            modified: None,
+            data,
+        }
+    }
+
+    /// Read a file from disk, taking its name and modification time from the filesystem
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, ImgSteganoError> {
+        let path = path.as_ref();
+        let name = path.file_name().and_then(|name| name.to_str()).ok_or(
+            ImgSteganoError::InvalidFileMetadata {
+                reason: "file name is not valid UTF-8",
+            },
+        )?;
+        let mut file = HiddenFile::new(name, fs::read(path)?);
+        file.modified = fs::metadata(path)?.modified().ok();
+        Ok(file)
+    }
+
+    /// Write the file into `dir` under its original name, returning the path written
+    ///
+    /// Directory components in the stored name are ignored, so a crafted
+    /// image cannot write outside of `dir`.
+    pub fn write_to_dir<P: AsRef<Path>>(&self, dir: P) -> Result<PathBuf, ImgSteganoError> {
+        let path = dir.as_ref().join(safe_file_name(&self.name)?);
+        fs::write(&path, &self.data)?;
+        if let Some(modified) = self.modified {
+            fs::File::options()
+                .write(true)
+                .open(&path)?
+                .set_modified(modified)?;
+        }
+        Ok(path)
+    }
+
+    /// Serialize the metadata record: name, MIME type and modification time
+    pub(crate) fn record(&self) -> Result<Vec<u8>, ImgSteganoError> {
+        let mut record = Vec::with_capacity(2 + self.name.len() + self.mime.len() + 8);
+        write_fields(&mut record, &self.name, &self.mime, self.modified)?;
+        Ok(record)
+    }
+
+    /// Rebuild a file from its metadata record and contents
+    pub(crate) fn from_record(record: &[u8], data: Vec<u8>) -> Result<Self, ImgSteganoError> {
+        let mut fields = record;
+        let (name, mime, modified) = read_fields(&mut fields)
+            .filter(|_| fields.is_empty())
+            .ok_or(ImgSteganoError::InvalidFileMetadata {
+                reason: "metadata record is malformed",
+            })?;
+        Ok(HiddenFile {
+            name,
+            mime,
+            modified,
+            data,
+        })
+    }
+}
+
+/// Hide a file, including its name, MIME type and modification time, in an image
+pub fn encode_file(input_image: Image, file: &HiddenFile) -> Result<Image, ImgSteganoError> {
+    encode_file_with_options(input_image, file, &EncodeOptions::default())
+}
+
+/// Hide a file in an image with the given options
+pub fn encode_file_with_options(
+    Image(input_image): Image,
+    file: &HiddenFile,
+    options: &EncodeOptions,
+) -> Result<Image, ImgSteganoError> {
+    // Validate file is not empty
+    if file.data.is_empty() {
+        return Err(ImgSteganoError::EmptyMessage);
+    }
+
+    let metadata = Metadata {
+        kind: KIND_FILE,
+        record: file.record()?,
+    };
+    let (header, body) = compress(&file.data, options.compression)?;
+    embed(input_image, header.with_metadata(metadata), &body, options)
+}
+
+/// Extract a file hidden with [`encode_file`]
+///
+/// Returns [`ImgSteganoError::NotAFile`] if the image carries a plain payload.
+pub fn extract_file(encoded_image: &Image) -> Result<HiddenFile, ImgSteganoError> {
+    extract_file_with_options(encoded_image, &DecodeOptions::default())
+}
+
+/// Extract a file hidden with [`encode_file_with_options`] and a stego key
+pub fn extract_file_with_options(
+    encoded_image: &Image,
+    options: &DecodeOptions,
+) -> Result<HiddenFile, ImgSteganoError> {
+    let (header, body, _) = extract_with_options(encoded_image, options)?;
+    ensure_plaintext(&header)?;
+    let record = match &header.metadata {
+        Some(metadata) if metadata.kind == KIND_FILE => metadata.record.clone(),
+        _ => return Err(ImgSteganoError::NotAFile),
+    };
+    HiddenFile::from_record(&record, decompress(&header, body)?)
+}
+
+/// Guess a MIME type from a file name's extension
+pub fn mime_from_name(name: &str) -> &'static str {
+    let extension = Path::new(name)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("txt" | "log") => "text/plain",
+        Some("md") => "text/markdown",
+        Some("csv") => "text/csv",
+        Some("html" | "htm") => "text/html",
+        Some("json") => "application/json",
+        Some("toml") => "application/toml",
+        Some("yaml" | "yml") => "application/yaml",
+        Some("xml") => "application/xml",
+        Some("pdf") => "application/pdf",
+        Some("zip") => "application/zip",
+        Some("gz") => "application/gzip",
+        Some("tar") => "application/x-tar",
+        Some("pem" | "key" | "crt") => "application/x-pem-file",
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        _ => DEFAULT_MIME,
+    }
+}
+
+/// Strip directory components from a stored name
+pub(crate) fn safe_file_name(name: &str) -> Result<&str, ImgSteganoError> {
+    Path::new(name)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or(ImgSteganoError::InvalidFileMetadata {
+            reason: "file name has no usable final component",
+        })
+}
+
+/// Append the name, MIME type and modification time fields to a record
+pub(crate) fn write_fields(
+    record: &mut Vec<u8>,
+    name: &str,
+    mime: &str,
+    modified: Option<SystemTime>,
+) -> Result<(), ImgSteganoError> {
+    if name.is_empty() || name.len() > u8::MAX as usize {
+        return Err(ImgSteganoError::InvalidFileMetadata {
+            reason: "file name must be between 1 and 255 bytes",
+        });
+    }
+    if mime.len() > u8::MAX as usize {
+        return Err(ImgSteganoError::InvalidFileMetadata {
+            reason: "MIME type must be at most 255 bytes",
+        });
+    }
+
+    // zero means the modification time is unknown
+    let modified = modified
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |duration| duration.as_secs());
+
+    record.push(name.len() as u8);
+    record.extend_from_slice(name.as_bytes());
+    record.push(mime.len() as u8);
+    record.extend_from_slice(mime.as_bytes());
+    record.extend_from_slice(&modified.to_be_bytes());
+    Ok(())
+}
+
+/// Read the fields written by [`write_fields`], advancing `fields` past them
+pub(crate) fn read_fields(fields: &mut &[u8]) -> Option<(String, String, Option<SystemTime>)> {
+    let name = take_string(fields)?;
+    let mime = take_string(fields)?;
+    let modified = u64::from_be_bytes(take(fields, 8)?.try_into().ok()?);
+    let modified = match modified {
+        0 => None,
+        secs => UNIX_EPOCH.checked_add(Duration::from_secs(secs)),
+    };
+    Some((name, mime, modified))
+}
+
+/// Split `len` bytes off the front of `fields`
+pub(crate) fn take<'a>(fields: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+    if fields.len() < len {
+        return None;
+    }
+    let (value, rest) = fields.split_at(len);
+    *fields = rest;
+    Some(value)
+}
+
+fn take_string(fields: &mut &[u8]) -> Option<String> {
+    let len = take(fields, 1)?[0] as usize;
+    String::from_utf8(take(fields, len)?.to_vec()).ok()
+}
@@ -0,0 +1,114 @@
+//! Arithmetic in GF(2^8) with the primitive polynomial x^8 + x^4 + x^3 + x^2 + 1
+
+const PRIMITIVE: u16 = 0x11d;
+
+const fn tables() -> ([u8; 512], [u8; 256]) {
+    let mut exp = [0u8; 512];
+    let mut log = [0u8; 256];
+    let mut x: u16 = 1;
+    let mut i = 0;
+    while i < 255 {
+        exp[i] = x as u8;
+        log[x as usize] = i as u8;
+        x <<= 1;
+        if x & 0x100 != 0 {
+            x ^= PRIMITIVE;
+        }
+        i += 1;
+    }
+    // duplicate the table so products of two logs never need a modulo
+    while i < 512 {
+        exp[i] = exp[i - 255];
+        i += 1;
+    }
+    (exp, log)
+}
+
+const TABLES: ([u8; 512], [u8; 256]) = tables();
+const EXP: [u8; 512] = TABLES.0;
+const LOG: [u8; 256] = TABLES.1;
+
+pub(crate) fn mul(a: u8, b: u8) -> u8 {
+    if a == 0 || b == 0 {
+        return 0;
+    }
+    EXP[LOG[a as usize] as usize + LOG[b as usize] as usize]
+}
+
+pub(crate) fn div(a: u8, b: u8) -> u8 {
+    assert!(b != 0, "division by zero in GF(256)");
+    if a == 0 {
+        return 0;
+    }
+    EXP[(LOG[a as usize] as usize + 255 - LOG[b as usize] as usize) % 255]
+}
+
+/// `a` raised to a possibly negative power
+pub(crate) fn pow(a: u8, power: i32) -> u8 {
+    if a == 0 {
+        return 0;
+    }
+    EXP[(LOG[a as usize] as i32 * power).rem_euclid(255) as usize]
+}
+
+pub(crate) fn inverse(a: u8) -> u8 {
+    div(1, a)
+}
+
+/// The generator element raised to `power`
+pub(crate) fn alpha(power: i32) -> u8 {
+    pow(2, power)
+}
+
+/// Polynomials are stored highest degree coefficient first
+pub(crate) mod poly {
+    use super::mul;
+
+    pub(crate) fn scale(p: &[u8], x: u8) -> Vec<u8> {
+        p.iter().map(|&c| mul(c, x)).collect()
+    }
+
+    pub(crate) fn add(p: &[u8], q: &[u8]) -> Vec<u8> {
+        let len = p.len().max(q.len());
+        let mut r = vec![0u8; len];
+        for (i, &c) in p.iter().enumerate() {
+            r[i + len - p.len()] = c;
+        }
+        for (i, &c) in q.iter().enumerate() {
+            r[i + len - q.len()] ^= c;
+        }
+        r
+    }
+
+    pub(crate) fn mul_poly(p: &[u8], q: &[u8]) -> Vec<u8> {
+        let mut r = vec![0u8; p.len() + q.len() - 1];
+        for (j, &qc) in q.iter().enumerate() {
+            for (i, &pc) in p.iter().enumerate() {
+                r[i + j] ^= mul(pc, qc);
+            }
+        }
+        r
+    }
+
+    pub(crate) fn eval(p: &[u8], x: u8) -> u8 {
+        p.iter().fold(0, |y, &c| mul(y, x) ^ c)
+    }
+
+    /// Remainder of dividing by a monic polynomial
+    pub(crate) fn rem(dividend: &[u8], divisor: &[u8]) -> Vec<u8> {
+        let mut out = dividend.to_vec();
+        let degree = divisor.len() - 1;
+        if dividend.len() <= degree {
+            return out;
+        }
+        for i in 0..dividend.len() - degree {
+            let coef = out[i];
+            if coef != 0 {
+                for (j, &d) in divisor.iter().enumerate().skip(1) {
+                    out[i + j] ^= mul(d, coef);
+                }
+            }
+        }
+        out.split_off(dividend.len() - degree)
+    }
+}
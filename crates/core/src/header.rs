@@ -0,0 +1,440 @@
+use crate::compress::COMPRESSED_SECTION_SIZE;
+use crate::lsb::{Layout, LAYOUT_SIZE};
+use crate::ImgSteganoError;
+
+/// Marker written at the start of every embedded payload
+pub(crate) const MAGIC: [u8; 4] = *b"ISTG";
+
+/// Current version of the embedded header format
+pub(crate) const VERSION: u8 = 1;
+
+/// Payload is encrypted, the header carries the salt and nonce
+pub(crate) const FLAG_ENCRYPTED: u8 = 0b0000_0001;
+
+/// Payload is encrypted to X25519 recipients, the header carries their stanzas
+pub(crate) const FLAG_RECIPIENTS: u8 = 0b0000_0010;
+
+/// Payload is signed, the header carries the signer key and signature
+pub(crate) const FLAG_SIGNED: u8 = 0b0000_0100;
+
+/// Payload is compressed, the header carries the algorithm and original length
+pub(crate) const FLAG_COMPRESSED: u8 = 0b0000_1000;
+
+/// Payload carries a typed metadata record describing its contents
+pub(crate) const FLAG_METADATA: u8 = 0b0001_0000;
+
+/// Payload is embedded with a non-default layout, the header records it
+pub(crate) const FLAG_LAYOUT: u8 = 0b0010_0000;
+
+/// Payload body is coded or placed differently, the header records how
+pub(crate) const FLAG_CODING: u8 = 0b0100_0000;
+
+/// Flags understood by this version of the decoder
+const KNOWN_FLAGS: u8 = FLAG_ENCRYPTED
+    | FLAG_RECIPIENTS
+    | FLAG_SIGNED
+    | FLAG_COMPRESSED
+    | FLAG_METADATA
+    | FLAG_LAYOUT
+    | FLAG_CODING;
+
+/// Size of the fixed header in bytes: magic, version, flags, payload length and checksum
+pub(crate) const HEADER_SIZE: usize = MAGIC.len() + 1 + 1 + 4 + 4;
+
+/// Size of the coding section: scheme, parameter and cost map
+pub(crate) const CODING_SECTION_SIZE: usize = 3;
+
+/// Size of the random salt fed to the key derivation function
+pub(crate) const SALT_SIZE: usize = 16;
+
+/// Size of the AEAD nonce
+pub(crate) const NONCE_SIZE: usize = 12;
+
+/// Size of an X25519 public key
+pub(crate) const KEY_SIZE: usize = 32;
+
+/// Size of a file key wrapped for one recipient, including its AEAD tag
+pub(crate) const WRAPPED_KEY_SIZE: usize = 32 + 16;
+
+/// Size of an Ed25519 signature
+pub(crate) const SIGNATURE_SIZE: usize = 64;
+
+/// Parameters needed to decrypt an encrypted payload
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Encryption {
+    pub salt: [u8; SALT_SIZE],
+    pub nonce: [u8; NONCE_SIZE],
+}
+
+/// File key wrapped for a single recipient
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Stanza {
+    /// Ephemeral public key used for the key agreement
+    pub ephemeral: [u8; KEY_SIZE],
+    pub wrapped_key: [u8; WRAPPED_KEY_SIZE],
+}
+
+/// Recipient stanzas and the nonce the payload was sealed with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Recipients {
+    pub stanzas: Vec<Stanza>,
+    pub nonce: [u8; NONCE_SIZE],
+}
+
+/// Ed25519 signature over the payload and the key that produced it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Signature {
+    pub signer: [u8; KEY_SIZE],
+    pub signature: [u8; SIGNATURE_SIZE],
+}
+
+/// Compression applied to the payload before it was embedded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Compressed {
+    pub algorithm: u8,
+    pub original_length: u32,
+}
+
+/// Typed record describing the payload, e.g. the name of an embedded file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Metadata {
+    pub kind: u8,
+    pub record: Vec<u8>,
+}
+
+/// How the body bits are coded and where they are placed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct Coding {
+    /// Code applied to the body bits, e.g. matrix embedding, `0` for none
+    pub scheme: u8,
+    pub parameter: u8,
+    /// Cost map ordering the body slots, `0` to follow the header
+    pub cost_map: u8,
+}
+
+impl Recipients {
+    fn size(&self) -> usize {
+        1 + self.stanzas.len() * (KEY_SIZE + WRAPPED_KEY_SIZE) + NONCE_SIZE
+    }
+}
+
+/// Header stored in the image ahead of the payload
+///
+/// The fixed part is always [`HEADER_SIZE`] bytes. Optional sections follow
+/// it, each present only when its flag is set: the layout first, since it
+/// is embedded along with the fixed part, then the others in flag bit order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Header {
+    pub version: u8,
+    pub length: u32,
+    /// CRC32 of the payload bytes
+    pub checksum: u32,
+    pub encryption: Option<Encryption>,
+    pub recipients: Option<Recipients>,
+    pub signature: Option<Signature>,
+    pub compressed: Option<Compressed>,
+    pub metadata: Option<Metadata>,
+    /// Layout the stream was embedded with
+    pub layout: Layout,
+    pub coding: Option<Coding>,
+}
+
+impl Header {
+    pub fn new(payload: &[u8]) -> Self {
+        Header {
+            version: VERSION,
+            length: payload.len() as u32,
+            checksum: crc32fast::hash(payload),
+            encryption: None,
+            recipients: None,
+            signature: None,
+            compressed: None,
+            metadata: None,
+            layout: Layout::default(),
+            coding: None,
+        }
+    }
+
+    #[cfg_attr(not(feature = "encrypt"), allow(dead_code))]
+    pub fn with_encryption(mut self, encryption: Encryption) -> Self {
+        self.encryption = Some(encryption);
+        self
+    }
+
+    #[cfg_attr(not(feature = "encrypt"), allow(dead_code))]
+    pub fn with_recipients(mut self, recipients: Recipients) -> Self {
+        self.recipients = Some(recipients);
+        self
+    }
+
+    #[cfg_attr(not(feature = "sign"), allow(dead_code))]
+    pub fn with_signature(mut self, signature: Signature) -> Self {
+        self.signature = Some(signature);
+        self
+    }
+
+    pub fn with_compression(mut self, compressed: Compressed) -> Self {
+        self.compressed = Some(compressed);
+        self
+    }
+
+    pub fn with_metadata(mut self, metadata: Metadata) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    pub fn with_layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    pub fn with_coding(mut self, coding: Coding) -> Self {
+        self.coding = Some(coding);
+        self
+    }
+
+    pub fn flags(&self) -> u8 {
+        let mut flags = 0;
+        if self.encryption.is_some() {
+            flags |= FLAG_ENCRYPTED;
+        }
+        if self.recipients.is_some() {
+            flags |= FLAG_RECIPIENTS;
+        }
+        if self.signature.is_some() {
+            flags |= FLAG_SIGNED;
+        }
+        if self.compressed.is_some() {
+            flags |= FLAG_COMPRESSED;
+        }
+        if self.metadata.is_some() {
+            flags |= FLAG_METADATA;
+        }
+        if self.layout != Layout::default() {
+            flags |= FLAG_LAYOUT;
+        }
+        if self.coding.is_some() {
+            flags |= FLAG_CODING;
+        }
+
+        flags
+    }
+
+    /// Size of the fixed header and layout section, which are always
+    /// embedded with the default layout
+    pub fn prefix_size(&self) -> usize {
+        if self.layout != Layout::default() {
+            HEADER_SIZE + LAYOUT_SIZE
+        } else {
+            HEADER_SIZE
+        }
+    }
+
+    /// Size of the optional sections following the fixed header
+    pub fn extension_size(&self) -> usize {
+        let mut size = self.prefix_size() - HEADER_SIZE;
+        if self.encryption.is_some() {
+            size += SALT_SIZE + NONCE_SIZE;
+        }
+        if let Some(recipients) = &self.recipients {
+            size += recipients.size();
+        }
+        if self.signature.is_some() {
+            size += KEY_SIZE + SIGNATURE_SIZE;
+        }
+        if self.compressed.is_some() {
+            size += COMPRESSED_SECTION_SIZE;
+        }
+        if let Some(metadata) = &self.metadata {
+            size += 1 + 2 + metadata.record.len();
+        }
+        if self.coding.is_some() {
+            size += CODING_SECTION_SIZE;
+        }
+        size
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.serialize(true)
+    }
+
+    /// The bytes a signature covers: the whole header but the signature
+    /// section itself
+    #[cfg_attr(not(feature = "sign"), allow(dead_code))]
+    pub fn signed_bytes(&self) -> Vec<u8> {
+        self.serialize(false)
+    }
+
+    fn serialize(&self, with_signature: bool) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_SIZE + self.extension_size());
+        bytes.extend_from_slice(&MAGIC);
+        bytes.push(self.version);
+        bytes.push(self.flags());
+        bytes.extend_from_slice(&self.length.to_be_bytes());
+        bytes.extend_from_slice(&self.checksum.to_be_bytes());
+        if self.layout != Layout::default() {
+            bytes.extend_from_slice(&self.layout.to_bytes());
+        }
+        if let Some(encryption) = self.encryption {
+            bytes.extend_from_slice(&encryption.salt);
+            bytes.extend_from_slice(&encryption.nonce);
+        }
+        if let Some(recipients) = &self.recipients {
+            bytes.push(recipients.stanzas.len() as u8);
+            for stanza in &recipients.stanzas {
+                bytes.extend_from_slice(&stanza.ephemeral);
+                bytes.extend_from_slice(&stanza.wrapped_key);
+            }
+            bytes.extend_from_slice(&recipients.nonce);
+        }
+        if let Some(signature) = self.signature.filter(|_| with_signature) {
+            bytes.extend_from_slice(&signature.signer);
+            bytes.extend_from_slice(&signature.signature);
+        }
+        if let Some(compressed) = &self.compressed {
+            bytes.push(compressed.algorithm);
+            bytes.extend_from_slice(&compressed.original_length.to_be_bytes());
+        }
+        if let Some(metadata) = &self.metadata {
+            bytes.push(metadata.kind);
+            bytes.extend_from_slice(&(metadata.record.len() as u16).to_be_bytes());
+            bytes.extend_from_slice(&metadata.record);
+        }
+        if let Some(coding) = self.coding {
+            bytes.push(coding.scheme);
+            bytes.push(coding.parameter);
+            bytes.push(coding.cost_map);
+        }
+        bytes
+    }
+
+    /// Read and validate a header from the start of an embedded byte stream
+    pub fn read<I: Iterator<Item = u8>>(bytes: &mut I) -> Result<Self, ImgSteganoError> {
+        let mut raw = [0u8; HEADER_SIZE];
+        // image is too small to even hold a header
+        read_exact(bytes, &mut raw).ok_or(ImgSteganoError::NoPayloadFound)?;
+
+        if raw[..4] != MAGIC {
+            return Err(ImgSteganoError::NoPayloadFound);
+        }
+
+        let version = raw[4];
+        if version != VERSION {
+            return Err(ImgSteganoError::UnsupportedVersion { version });
+        }
+        let flags = raw[5];
+        if flags & !KNOWN_FLAGS != 0 {
+            return Err(ImgSteganoError::UnsupportedFlags { flags });
+        }
+
+        let mut header = Header {
+            version,
+            length: u32::from_be_bytes([raw[6], raw[7], raw[8], raw[9]]),
+            checksum: u32::from_be_bytes([raw[10], raw[11], raw[12], raw[13]]),
+            encryption: None,
+            recipients: None,
+            signature: None,
+            compressed: None,
+            metadata: None,
+            layout: Layout::default(),
+            coding: None,
+        };
+
+        if flags & FLAG_LAYOUT != 0 {
+            let mut raw = [0u8; LAYOUT_SIZE];
+            read_exact(bytes, &mut raw).ok_or(ImgSteganoError::TruncatedPayload)?;
+            header.layout = Layout::from_bytes(raw);
+        }
+
+        if flags & FLAG_ENCRYPTED != 0 {
+            let mut encryption = Encryption {
+                salt: [0; SALT_SIZE],
+                nonce: [0; NONCE_SIZE],
+            };
+            read_exact(bytes, &mut encryption.salt).ok_or(ImgSteganoError::TruncatedPayload)?;
+            read_exact(bytes, &mut encryption.nonce).ok_or(ImgSteganoError::TruncatedPayload)?;
+            header.encryption = Some(encryption);
+        }
+
+        if flags & FLAG_RECIPIENTS != 0 {
+            let count = bytes.next().ok_or(ImgSteganoError::TruncatedPayload)?;
+            let mut recipients = Recipients {
+                stanzas: Vec::with_capacity(count as usize),
+                nonce: [0; NONCE_SIZE],
+            };
+            for _ in 0..count {
+                let mut stanza = Stanza {
+                    ephemeral: [0; KEY_SIZE],
+                    wrapped_key: [0; WRAPPED_KEY_SIZE],
+                };
+                read_exact(bytes, &mut stanza.ephemeral)
+                    .ok_or(ImgSteganoError::TruncatedPayload)?;
+                read_exact(bytes, &mut stanza.wrapped_key)
+                    .ok_or(ImgSteganoError::TruncatedPayload)?;
+                recipients.stanzas.push(stanza);
+            }
+            read_exact(bytes, &mut recipients.nonce).ok_or(ImgSteganoError::TruncatedPayload)?;
+            header.recipients = Some(recipients);
+        }
+
+        if flags & FLAG_SIGNED != 0 {
+            let mut signature = Signature {
+                signer: [0; KEY_SIZE],
+                signature: [0; SIGNATURE_SIZE],
+            };
+            read_exact(bytes, &mut signature.signer).ok_or(ImgSteganoError::TruncatedPayload)?;
+            read_exact(bytes, &mut signature.signature).ok_or(ImgSteganoError::TruncatedPayload)?;
+            header.signature = Some(signature);
+        }
+
+        if flags & FLAG_COMPRESSED != 0 {
+            let mut raw = [0u8; 5];
+            read_exact(bytes, &mut raw).ok_or(ImgSteganoError::TruncatedPayload)?;
+            header.compressed = Some(Compressed {
+                algorithm: raw[0],
+                original_length: u32::from_be_bytes([raw[1], raw[2], raw[3], raw[4]]),
+            });
+        }
+
+        if flags & FLAG_METADATA != 0 {
+            let mut raw = [0u8; 3];
+            read_exact(bytes, &mut raw).ok_or(ImgSteganoError::TruncatedPayload)?;
+            let mut record = vec![0u8; u16::from_be_bytes([raw[1], raw[2]]) as usize];
+            read_exact(bytes, &mut record).ok_or(ImgSteganoError::TruncatedPayload)?;
+            header.metadata = Some(Metadata {
+                kind: raw[0],
+                record,
+            });
+        }
+
+        if flags & FLAG_CODING != 0 {
+            let mut raw = [0u8; CODING_SECTION_SIZE];
+            read_exact(bytes, &mut raw).ok_or(ImgSteganoError::TruncatedPayload)?;
+            header.coding = Some(Coding {
+                scheme: raw[0],
+                parameter: raw[1],
+                cost_map: raw[2],
+            });
+        }
+        Ok(header)
+    }
+
+    /// Check that a decoded payload matches the checksum recorded at encode time
+    pub fn verify(&self, payload: &[u8]) -> Result<(), ImgSteganoError> {
+        let actual = crc32fast::hash(payload);
+        if actual != self.checksum {
+            return Err(ImgSteganoError::ChecksumMismatch {
+                expected: self.checksum,
+                actual,
+            });
+        }
+        Ok(())
+    }
+}
+
+fn read_exact<I: Iterator<Item = u8>>(bytes: &mut I, buf: &mut [u8]) -> Option<()> {
+    for byte in buf.iter_mut() {
+        *byte = bytes.next()?;
+    }
+    Some(())
+}
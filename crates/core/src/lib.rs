@@ -1,20 +1,78 @@
+mod adaptive;
+mod archive;
+mod compress;
 mod decode;
+mod distortion;
 mod encode;
+#[cfg(feature = "encrypt")]
+mod encrypt;
 mod error;
+mod fec;
+mod file;
+mod gf256;
+mod header;
+mod lsb;
+mod matrix;
+mod options;
+mod pvd;
+#[cfg(feature = "encrypt")]
+mod recipients;
+mod reversible;
+mod shamir;
+#[cfg(feature = "sign")]
+mod sign;
+mod split;
+mod stream;
+mod traversal;
+mod trellis;
 
 use std::path::PathBuf;
 
+pub use archive::*;
+pub use compress::Compression;
 pub use decode::*;
+pub use distortion::{CostFunction, Distortion};
 pub use encode::*;
+#[cfg(feature = "encrypt")]
+pub use encrypt::*;
 pub use error::ImgSteganoError;
+pub use file::{
+    encode_file, encode_file_with_options, extract_file, extract_file_with_options, mime_from_name,
+    HiddenFile, DEFAULT_MIME,
+};
+use header::HEADER_SIZE;
 pub use image::ImageFormat;
 use image::{DynamicImage, GenericImageView};
+pub use lsb::Channels;
+use lsb::LAYOUT_SIZE;
+pub use options::{DecodeOptions, EncodeOptions};
+pub use pvd::{decode_pvd, encode_pvd};
+#[cfg(feature = "encrypt")]
+pub use recipients::*;
+pub use reversible::{encode_reversible, extract_and_restore};
+pub use shamir::*;
+#[cfg(feature = "sign")]
+pub use sign::*;
+pub use split::*;
+pub use stream::{StegoReader, StegoWriter};
 
 /// Calculate the maximum message capacity (in bytes) for an image
-pub fn calculate_capacity(width: u32, height: u32) -> usize {
-    // Each pixel has 3 RGB channels, each can store 1 bit
-    // Divide by 8 to get bytes, subtract 1 for null terminator
-    ((width as usize * height as usize * 3) / 8).saturating_sub(1)
+///
+/// `bits_per_channel` is the number of low bits of each channel used to
+/// carry data, see [`EncodeOptions::with_bits_per_channel`]. Assumes a color
+/// image; grayscale images carry one sample per pixel instead of three, use
+/// [`Image::capacity`] to account for the actual color type.
+pub fn calculate_capacity(width: u32, height: u32, bits_per_channel: u8) -> usize {
+    // Each pixel has 3 RGB channels, each can store `bits_per_channel` bits.
+    // The header starts with a prefix stored one bit per sample, plus the
+    // layout for more than one bit, see `lsb::stream_slots`
+    let prefix_size = if bits_per_channel > 1 {
+        HEADER_SIZE + LAYOUT_SIZE
+    } else {
+        HEADER_SIZE
+    };
+    let samples = (width as usize * height as usize * 3).saturating_sub(prefix_size * 8);
+    samples * bits_per_channel as usize / 8
 }
 
 #[derive(Debug, Clone)]
@@ -41,9 +99,64 @@ impl Image {
     }
 
     /// Get the maximum message capacity (in bytes) for this image
+    ///
+    /// Grayscale images hold one sample per pixel, so a third of the capacity
+    /// of a color image of the same size.
     pub fn capacity(&self) -> usize {
-        let (width, height) = self.0.dimensions();
-        calculate_capacity(width, height)
+        self.capacity_with_options(&EncodeOptions::default())
+    }
+
+    /// Get the maximum message capacity (in bytes) when encoding with `options`
+    ///
+    /// Selected channels the image does not have, such as alpha in an RGB
+    /// image, do not count towards the capacity, and neither do the header
+    /// sections the options add. Compression and error correction are not
+    /// accounted for.
+    pub fn capacity_with_options(&self, options: &EncodeOptions) -> usize {
+        let layout = options.layout.normalized(self.0.color());
+        let mut header = header::Header::new(&[]).with_layout(layout);
+        if let Some(coding) = encode::coding(options) {
+            header = header.with_coding(coding);
+        }
+        let key = options.stego_key.as_ref();
+        lsb::stream_capacity(&self.0, layout, key, header.prefix_size())
+            .saturating_sub(HEADER_SIZE + header.extension_size())
+    }
+
+    /// Estimate how many bytes of payload like `sample` this image can hold
+    ///
+    /// With compression enabled the effective capacity depends on the data,
+    /// so the ratio achieved on `sample` is extrapolated to the whole image.
+    pub fn estimated_capacity(
+        &self,
+        sample: &[u8],
+        compression: Compression,
+    ) -> Result<usize, ImgSteganoError> {
+        let capacity = self.capacity();
+        match compression.apply(sample)? {
+            Some((_, compressed)) => {
+                let available = capacity.saturating_sub(compress::COMPRESSED_SECTION_SIZE);
+                Ok(available * sample.len() / compressed.len().max(1))
+            }
+            None => Ok(capacity),
+        }
+    }
+
+    /// Get the maximum payload size (in bytes) for [`encode_reversible`]
+    ///
+    /// Depends on the content: it grows with the number of samples sharing
+    /// the most frequent value, so smooth images and images with large flat
+    /// backgrounds hold the most.
+    pub fn reversible_capacity(&self) -> usize {
+        reversible::capacity(&self.0)
+    }
+
+    /// Get the maximum payload size (in bytes) for [`encode_pvd`]
+    ///
+    /// Depends on the content: pairs of neighbouring samples that differ a
+    /// lot hold more bits than smooth ones, so detailed images hold the most.
+    pub fn pvd_capacity(&self) -> usize {
+        pvd::capacity(&self.0)
     }
 
     /// Get the dimensions of the image
@@ -57,3 +170,9 @@ impl From<DynamicImage> for Image {
         Image(value)
     }
 }
+
+impl From<Image> for DynamicImage {
+    fn from(Image(value): Image) -> Self {
+        value
+    }
+}
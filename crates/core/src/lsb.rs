@@ -0,0 +1,514 @@
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::iter::Peekable;
+use std::ops::BitOr;
+
+use rand_chacha::ChaCha20Rng;
+use rand_core::{RngCore, SeedableRng};
+
+use crate::traversal::{self, StegoKey};
+use crate::ImgSteganoError;
+use image::{ColorType, DynamicImage, GenericImageView};
+
+/// Largest number of low bits per channel that may carry payload data
+///
+/// Up to half of each sample: 4 bits of 8-bit samples, 8 bits of 16-bit ones.
+pub(crate) fn max_bits_per_channel(color: ColorType) -> u8 {
+    color.bytes_per_pixel() / color.channel_count() * 4
+}
+
+/// Set of color channels that carry payload data
+///
+/// Channels combine with `|`, e.g. `Channels::RED | Channels::GREEN`. Data
+/// is written to the selected channels of each pixel in R, G, B, A order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Channels(u8);
+
+impl Channels {
+    pub const RED: Channels = Channels(0b0001);
+    pub const GREEN: Channels = Channels(0b0010);
+    pub const BLUE: Channels = Channels(0b0100);
+    /// Only usable with images that have an alpha channel
+    pub const ALPHA: Channels = Channels(0b1000);
+    pub const RGB: Channels = Channels(0b0111);
+    pub const RGBA: Channels = Channels(0b1111);
+
+    /// Whether every channel in `other` is selected
+    pub fn contains(self, other: Channels) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Number of selected channels
+    pub fn count(self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    pub(crate) fn bits(self) -> u8 {
+        self.0
+    }
+
+    pub(crate) fn from_bits(bits: u8) -> Channels {
+        Channels(bits)
+    }
+}
+
+impl Default for Channels {
+    fn default() -> Self {
+        Channels::RGB
+    }
+}
+
+impl BitOr for Channels {
+    type Output = Channels;
+
+    fn bitor(self, rhs: Channels) -> Channels {
+        Channels(self.0 | rhs.0)
+    }
+}
+
+/// Size of a layout recorded in a payload header
+pub(crate) const LAYOUT_SIZE: usize = 2;
+
+/// How payload bits are laid out in the image samples
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Layout {
+    /// Number of low bits of each channel that carry payload data
+    pub bits_per_channel: u8,
+    pub channels: Channels,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Layout {
+            bits_per_channel: 1,
+            channels: Channels::default(),
+        }
+    }
+}
+
+impl Layout {
+    /// The equivalent layout as recorded for an image of `color`
+    ///
+    /// A gray sample stands in for all of R, G and B, so selecting any color
+    /// channel of a grayscale image selects it. Alpha is dropped from images
+    /// without it.
+    pub fn normalized(self, color: ColorType) -> Layout {
+        let mut channels = self.channels;
+        if !color.has_color() && channels.0 & Channels::RGB.0 != 0 {
+            channels = channels | Channels::RGB;
+        }
+        if !color.has_alpha() {
+            channels = Channels(channels.0 & !Channels::ALPHA.0);
+        }
+        Layout { channels, ..self }
+    }
+
+    /// Positions of the selected samples within a pixel of `color`
+    pub fn sample_indices(&self, color: ColorType) -> Vec<usize> {
+        let mut indices = Vec::with_capacity(4);
+        if color.has_color() {
+            let colors = [Channels::RED, Channels::GREEN, Channels::BLUE];
+            indices.extend((0..3).filter(|&i| self.channels.contains(colors[i])));
+        } else if self.channels.0 & Channels::RGB.0 != 0 {
+            indices.push(0);
+        }
+        if color.has_alpha() && self.channels.contains(Channels::ALPHA) {
+            indices.push(color.channel_count() as usize - 1);
+        }
+        indices
+    }
+
+    /// The layout as recorded in a payload header
+    pub fn to_bytes(self) -> [u8; LAYOUT_SIZE] {
+        [self.bits_per_channel, self.channels.bits()]
+    }
+
+    pub fn from_bytes(bytes: [u8; LAYOUT_SIZE]) -> Layout {
+        Layout {
+            bits_per_channel: bytes[0],
+            channels: Channels::from_bits(bytes[1]),
+        }
+    }
+
+    /// Check the layout can be used to embed into `image`
+    pub fn validate(&self, image: &DynamicImage) -> Result<(), ImgSteganoError> {
+        if !(1..=max_bits_per_channel(image.color())).contains(&self.bits_per_channel) {
+            return Err(ImgSteganoError::InvalidOptions {
+                reason: "bits per channel must be between 1 and 4, or 8 for 16-bit images",
+            });
+        }
+        if self.channels.count() == 0 || !Channels::RGBA.contains(self.channels) {
+            return Err(ImgSteganoError::InvalidOptions {
+                reason: "at least one of the R, G, B and A channels must be selected",
+            });
+        }
+        if self.channels.contains(Channels::ALPHA) && !image.color().has_alpha() {
+            return Err(ImgSteganoError::InvalidOptions {
+                reason: "alpha channel selected but the image has no alpha",
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Convert images with floating point samples into the closest 16-bit color type
+///
+/// 8 and 16-bit images are left untouched, so their color type, bit depth,
+/// alpha and every sample outside the payload bits are preserved.
+pub(crate) fn normalize(image: &mut DynamicImage) {
+    *image = match image {
+        DynamicImage::ImageRgb32F(_) => DynamicImage::ImageRgb16(image.to_rgb16()),
+        DynamicImage::ImageRgba32F(_) => DynamicImage::ImageRgba16(image.to_rgba16()),
+        _ => return,
+    };
+}
+
+/// Interleaved samples of an image, widened to 16 bits when read
+pub(crate) enum Samples<'a> {
+    Bytes(Cow<'a, [u8]>),
+    Words(Cow<'a, [u16]>),
+}
+
+impl<'a> Samples<'a> {
+    pub fn new(image: &'a DynamicImage) -> Self {
+        match image {
+            DynamicImage::ImageLuma8(buffer) => Samples::Bytes(Cow::Borrowed(buffer)),
+            DynamicImage::ImageLumaA8(buffer) => Samples::Bytes(Cow::Borrowed(buffer)),
+            DynamicImage::ImageRgb8(buffer) => Samples::Bytes(Cow::Borrowed(buffer)),
+            DynamicImage::ImageRgba8(buffer) => Samples::Bytes(Cow::Borrowed(buffer)),
+            DynamicImage::ImageLuma16(buffer) => Samples::Words(Cow::Borrowed(buffer)),
+            DynamicImage::ImageLumaA16(buffer) => Samples::Words(Cow::Borrowed(buffer)),
+            DynamicImage::ImageRgb16(buffer) => Samples::Words(Cow::Borrowed(buffer)),
+            DynamicImage::ImageRgba16(buffer) => Samples::Words(Cow::Borrowed(buffer)),
+            _ => {
+                let mut image = image.clone();
+                normalize(&mut image);
+                match Samples::new(&image) {
+                    Samples::Bytes(samples) => Samples::Bytes(Cow::Owned(samples.into_owned())),
+                    Samples::Words(samples) => Samples::Words(Cow::Owned(samples.into_owned())),
+                }
+            }
+        }
+    }
+
+    pub fn get(&self, index: usize) -> u16 {
+        match self {
+            Samples::Bytes(samples) => samples[index] as u16,
+            Samples::Words(samples) => samples[index],
+        }
+    }
+}
+
+/// Mutable interleaved samples of a normalized image
+pub(crate) enum SamplesMut<'a> {
+    Bytes(&'a mut [u8]),
+    Words(&'a mut [u16]),
+}
+
+impl<'a> SamplesMut<'a> {
+    pub fn new(image: &'a mut DynamicImage) -> Self {
+        match image {
+            DynamicImage::ImageLuma8(buffer) => SamplesMut::Bytes(buffer),
+            DynamicImage::ImageLumaA8(buffer) => SamplesMut::Bytes(buffer),
+            DynamicImage::ImageRgb8(buffer) => SamplesMut::Bytes(buffer),
+            DynamicImage::ImageRgba8(buffer) => SamplesMut::Bytes(buffer),
+            DynamicImage::ImageLuma16(buffer) => SamplesMut::Words(buffer),
+            DynamicImage::ImageLumaA16(buffer) => SamplesMut::Words(buffer),
+            DynamicImage::ImageRgb16(buffer) => SamplesMut::Words(buffer),
+            DynamicImage::ImageRgba16(buffer) => SamplesMut::Words(buffer),
+            _ => unreachable!("images are normalized before embedding"),
+        }
+    }
+
+    pub fn get(&self, index: usize) -> u16 {
+        match self {
+            SamplesMut::Bytes(samples) => samples[index] as u16,
+            SamplesMut::Words(samples) => samples[index],
+        }
+    }
+
+    /// Largest value a sample can take
+    pub fn max(&self) -> u16 {
+        match self {
+            SamplesMut::Bytes(_) => u8::MAX as u16,
+            SamplesMut::Words(_) => u16::MAX,
+        }
+    }
+
+    pub fn set(&mut self, index: usize, value: u16) {
+        match self {
+            SamplesMut::Bytes(samples) => samples[index] = value as u8,
+            SamplesMut::Words(samples) => samples[index] = value,
+        }
+    }
+
+    /// Move sample `index` to `replaced`, which differs from it only in the
+    /// low `bits` bits
+    ///
+    /// With `rng` (LSB matching) the sample is instead moved to whichever
+    /// value with the same low bits is closest, choosing at random between
+    /// equally close ones and never leaving the sample range.
+    fn change(&mut self, index: usize, replaced: u16, bits: usize, rng: Option<&mut ChaCha20Rng>) {
+        let current = self.get(index) as i32;
+        let replaced = replaced as i32;
+        let value = match rng {
+            Some(rng) if replaced != current => {
+                let max = self.max() as i32;
+                let step = 1 << bits;
+                let distance = |value: i32| (value - current).abs();
+                let other = if replaced > current {
+                    replaced - step
+                } else {
+                    replaced + step
+                };
+                let closer = distance(other).cmp(&distance(replaced));
+                let in_range = (0..=max).contains(&other);
+                match closer {
+                    Ordering::Less if in_range => other,
+                    Ordering::Equal if in_range && rng.next_u32() & 1 == 1 => other,
+                    _ => replaced,
+                }
+            }
+            _ => replaced,
+        };
+        self.set(index, value as u16);
+    }
+}
+
+/// Sample indices of the payload slots of an image, in the order they are visited
+///
+/// Each slot is one selected channel of one pixel and carries
+/// `bits_per_channel` bits.
+pub(crate) fn slots(
+    image: &DynamicImage,
+    layout: Layout,
+    key: Option<&StegoKey>,
+) -> impl Iterator<Item = usize> {
+    let color = image.color();
+    let samples_per_pixel = color.channel_count() as usize;
+    let indices = layout.sample_indices(color);
+    let (width, height) = image.dimensions();
+    let pixels = width as usize * height as usize;
+    traversal::order(pixels * indices.len(), key)
+        .map(move |slot| slot / indices.len() * samples_per_pixel + indices[slot % indices.len()])
+}
+
+/// Slots carrying a stream after its leading `prefix_size` bytes
+///
+/// The prefix (the fixed header and its layout, or an error correction
+/// preamble) is always embedded with the default layout, so a decoder can
+/// find it without knowing `layout`. The rest of the stream follows in the
+/// slots of `layout`, skipping the samples the prefix occupies.
+pub(crate) fn stream_slots(
+    image: &DynamicImage,
+    layout: Layout,
+    key: Option<&StegoKey>,
+    prefix_size: usize,
+) -> impl Iterator<Item = usize> {
+    let prefix: HashSet<usize> = slots(image, Layout::default(), key)
+        .take(prefix_size * 8)
+        .collect();
+    slots(image, layout, key).filter(move |index| !prefix.contains(index))
+}
+
+/// Number of stream bytes that fit in the image, prefix included
+pub(crate) fn stream_capacity(
+    image: &DynamicImage,
+    layout: Layout,
+    key: Option<&StegoKey>,
+    prefix_size: usize,
+) -> usize {
+    let color = image.color();
+    let samples_per_pixel = color.channel_count() as usize;
+    let indices = layout.sample_indices(color);
+    let prefix: Vec<usize> = slots(image, Layout::default(), key)
+        .take(prefix_size * 8)
+        .collect();
+    let shared = prefix
+        .iter()
+        .filter(|&&index| indices.contains(&(index % samples_per_pixel)))
+        .count();
+    let (width, height) = image.dimensions();
+    let slots = width as usize * height as usize * indices.len() - shared;
+    prefix.len() / 8 + slots * layout.bits_per_channel as usize / 8
+}
+
+/// Write a stream, its leading `prefix_size` bytes with the default layout
+/// and the rest with `layout`, see [`stream_slots`]
+pub(crate) fn write_stream(
+    image: &mut DynamicImage,
+    layout: Layout,
+    key: Option<&StegoKey>,
+    matching: bool,
+    prefix_size: usize,
+    stream: &[u8],
+) {
+    let (prefix, rest) = stream.split_at(prefix_size.min(stream.len()));
+    write_bytes(image, Layout::default(), key, matching, prefix);
+    let slots = stream_slots(image, layout, key, prefix_size);
+    write_slots(image, layout, slots, matching, 0, rest);
+}
+
+/// Lazily read back a stream written with [`write_stream`]
+pub(crate) fn read_stream<'a>(
+    image: &'a DynamicImage,
+    layout: Layout,
+    key: Option<&StegoKey>,
+    prefix_size: usize,
+) -> impl Iterator<Item = u8> + 'a {
+    read_bytes(image, Layout::default(), key)
+        .take(prefix_size)
+        .chain(read_slots(
+            image,
+            layout,
+            stream_slots(image, layout, key, prefix_size),
+        ))
+}
+
+/// Write bytes into the LSBs of the image, most significant bit first
+pub(crate) fn write_bytes(
+    image: &mut DynamicImage,
+    layout: Layout,
+    key: Option<&StegoKey>,
+    matching: bool,
+    bytes: &[u8],
+) {
+    let slots = slots(image, layout, key);
+    write_slots(image, layout, slots, matching, 0, bytes);
+}
+
+/// Write position in a sequence of slots, for writing bytes over several calls
+///
+/// Keeps its place in the slots, so each write only visits the slots it
+/// fills however far into the image it starts.
+pub(crate) struct Cursor {
+    slots: Peekable<Box<dyn Iterator<Item = usize>>>,
+    /// Bits already written into the next slot
+    within: usize,
+}
+
+impl Cursor {
+    /// Start `offset` bits into `slots`
+    pub fn new(
+        slots: impl Iterator<Item = usize> + 'static,
+        layout: Layout,
+        offset: usize,
+    ) -> Self {
+        let bits_per_channel = layout.bits_per_channel as usize;
+        let slots: Box<dyn Iterator<Item = usize>> = Box::new(slots);
+        let mut slots = slots.peekable();
+        slots
+            .by_ref()
+            .take(offset / bits_per_channel)
+            .for_each(drop);
+        Cursor {
+            slots,
+            within: offset % bits_per_channel,
+        }
+    }
+
+    /// Write bytes at the cursor and move past them
+    pub fn write(
+        &mut self,
+        image: &mut DynamicImage,
+        layout: Layout,
+        matching: bool,
+        bytes: &[u8],
+    ) {
+        let bits_per_channel = layout.bits_per_channel as usize;
+        let end = self.within + bytes.len() * 8;
+        // a partly filled last slot stays next in line
+        let mut slots: Vec<usize> = self.slots.by_ref().take(end / bits_per_channel).collect();
+        if !end.is_multiple_of(bits_per_channel) {
+            slots.extend(self.slots.peek());
+        }
+        write_slots(
+            image,
+            layout,
+            slots.into_iter(),
+            matching,
+            self.within,
+            bytes,
+        );
+        self.within = end % bits_per_channel;
+    }
+}
+
+/// Write bytes into the given slots, starting `within` bits into the first
+///
+/// With `matching`, samples whose payload bits differ are moved up or down
+/// instead of having the bits replaced, see [`SamplesMut::change`]. The
+/// image must have been passed through [`normalize`].
+pub(crate) fn write_slots(
+    image: &mut DynamicImage,
+    layout: Layout,
+    mut slots: impl Iterator<Item = usize>,
+    matching: bool,
+    mut within: usize,
+    bytes: &[u8],
+) {
+    let bits_per_channel = layout.bits_per_channel as usize;
+    let mut rng = matching.then(ChaCha20Rng::from_entropy);
+    let mut samples = SamplesMut::new(image);
+    let mut message_bits = bytes
+        .iter()
+        .flat_map(|byte| (0..8).rev().map(move |i| (byte >> i) & 1))
+        .peekable();
+
+    while message_bits.peek().is_some() {
+        let Some(index) = slots.next() else {
+            break;
+        };
+        let mut replaced = samples.get(index);
+        // the first bit of each channel goes into its highest payload bit
+        for shift in (0..bits_per_channel - within).rev() {
+            let Some(bit) = message_bits.next() else {
+                break;
+            };
+            replaced = (replaced & !(1 << shift)) | ((bit as u16) << shift);
+        }
+        within = 0;
+        samples.change(index, replaced, bits_per_channel, rng.as_mut());
+    }
+}
+
+/// Lazily read bytes back from the LSBs of the image
+pub(crate) fn read_bytes<'a>(
+    image: &'a DynamicImage,
+    layout: Layout,
+    key: Option<&StegoKey>,
+) -> impl Iterator<Item = u8> + 'a {
+    read_slots(image, layout, slots(image, layout, key))
+}
+
+/// Lazily read bytes back from the given slots of the image
+pub(crate) fn read_slots<'a>(
+    image: &'a DynamicImage,
+    layout: Layout,
+    slots: impl Iterator<Item = usize> + 'a,
+) -> impl Iterator<Item = u8> + 'a {
+    let bits_per_channel = layout.bits_per_channel;
+    let samples = Samples::new(image);
+
+    slots
+        .map(move |index| samples.get(index))
+        .flat_map(move |channel| {
+            (0..bits_per_channel)
+                .rev()
+                .map(move |shift| ((channel >> shift) & 1) as u8)
+        })
+        .scan((0u8, 0), |(current_byte, bit_count), bit| {
+            *current_byte = (*current_byte << 1) | bit;
+            *bit_count += 1;
+            if *bit_count == 8 {
+                let byte = *current_byte;
+                *current_byte = 0;
+                *bit_count = 0;
+                Some(Some(byte))
+            } else {
+                Some(None)
+            }
+        })
+        .flatten()
+}
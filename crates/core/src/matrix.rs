@@ -0,0 +1,147 @@
+use crate::header::Coding;
+use crate::{trellis, ImgSteganoError};
+
+/// Body bits are stored as they are
+pub(crate) const SCHEME_NONE: u8 = 0;
+
+/// Coding scheme of Hamming matrix embedding, the parameter is `k`
+pub(crate) const SCHEME_HAMMING: u8 = 1;
+
+/// Largest `k` considered, giving blocks of 65535 cover bits
+const MAX_K: u8 = 16;
+
+/// Number of cover bits needed to embed `length` bytes in blocks of `2^k - 1`
+///
+/// Returns `None` if the count overflows, which only an untrusted header
+/// can ask for.
+pub(crate) fn cover_bits(length: usize, k: u8) -> Option<usize> {
+    length
+        .checked_mul(8)?
+        .div_ceil(k as usize)
+        .checked_mul((1 << k) - 1)
+}
+
+/// Pick the Hamming code `k` for `length` bytes given `available` cover bits
+///
+/// Larger `k` changes fewer samples per message bit (at most one in every
+/// `2^k - 1`) but needs more cover, so the largest `k` that still fits is
+/// chosen. Returns `None` if the payload does not fit even with `k = 1`.
+pub(crate) fn choose(length: usize, available: usize) -> Option<u8> {
+    (1..=MAX_K)
+        .rev()
+        .find(|&k| cover_bits(length, k).is_some_and(|bits| bits <= available))
+}
+
+/// Number of embedded bytes holding a `length` byte body coded with `coding`
+///
+/// Returns [`ImgSteganoError::InvalidLength`] if that is more than the
+/// `available` bytes.
+pub(crate) fn coded_size(
+    length: usize,
+    coding: Option<Coding>,
+    available: usize,
+) -> Result<usize, ImgSteganoError> {
+    let size = match coding {
+        Some(coding) if coding.scheme == trellis::SCHEME_TRELLIS => {
+            trellis::coded_size(length, trellis_width(coding)?)
+        }
+        Some(coding) if coding.scheme != SCHEME_NONE => {
+            cover_bits(length, hamming_k(coding)?).map(|bits| bits.div_ceil(8))
+        }
+        _ => Some(length),
+    };
+    size.filter(|&size| size <= available)
+        .ok_or(ImgSteganoError::InvalidLength { length, available })
+}
+
+/// Recover a `length` byte body from its embedded bytes
+pub(crate) fn decode(
+    stored: Vec<u8>,
+    length: usize,
+    coding: Option<Coding>,
+) -> Result<Vec<u8>, ImgSteganoError> {
+    let Some(coding) = coding.filter(|coding| coding.scheme != SCHEME_NONE) else {
+        return Ok(stored);
+    };
+    if coding.scheme == trellis::SCHEME_TRELLIS {
+        return Ok(trellis::decode(&stored, length, trellis_width(coding)?));
+    }
+    let k = hamming_k(coding)?;
+    let n = (1 << k) - 1;
+    let message_bits = (0..(length * 8).div_ceil(k as usize))
+        .flat_map(|block| {
+            let syndrome = syndrome(&stored, block * n, n);
+            (0..k).rev().map(move |i| ((syndrome >> i) & 1) as u8)
+        })
+        .take(length * 8);
+    Ok(pack(message_bits))
+}
+
+/// Embed `message` into the `cover` bits, flipping at most one bit per block
+///
+/// Each block of `2^k - 1` cover bits carries `k` message bits as its
+/// syndrome: the XOR of the (1-based) positions of its set bits. Flipping
+/// the bit at position `syndrome ^ message` makes the syndrome equal the
+/// message, and no bit needs flipping when it already does.
+pub(crate) fn embed(cover: &mut [u8], message: &[u8], k: u8) {
+    let n = (1 << k) - 1;
+    let bits: Vec<u8> = unpack(message).collect();
+    for (block, chunk) in bits.chunks(k as usize).enumerate() {
+        // a short last chunk is padded with zero bits
+        let value = chunk
+            .iter()
+            .chain(std::iter::repeat(&0))
+            .take(k as usize)
+            .fold(0, |value, &bit| (value << 1) | bit as usize);
+        let flip = syndrome(cover, block * n, n) ^ value;
+        if flip != 0 {
+            let position = block * n + flip - 1;
+            cover[position / 8] ^= 0x80 >> (position % 8);
+        }
+    }
+}
+
+fn hamming_k(coding: Coding) -> Result<u8, ImgSteganoError> {
+    if coding.scheme != SCHEME_HAMMING || !(1..=MAX_K).contains(&coding.parameter) {
+        return Err(ImgSteganoError::UnsupportedCoding {
+            scheme: coding.scheme,
+            parameter: coding.parameter,
+        });
+    }
+    Ok(coding.parameter)
+}
+
+fn trellis_width(coding: Coding) -> Result<u8, ImgSteganoError> {
+    if coding.parameter == 0 {
+        return Err(ImgSteganoError::UnsupportedCoding {
+            scheme: coding.scheme,
+            parameter: coding.parameter,
+        });
+    }
+    Ok(coding.parameter)
+}
+
+/// XOR of the 1-based positions of the set bits in `n` bits from `offset`
+fn syndrome(bits: &[u8], offset: usize, n: usize) -> usize {
+    (1..=n)
+        .filter(|&i| {
+            let position = offset + i - 1;
+            bits[position / 8] & (0x80 >> (position % 8)) != 0
+        })
+        .fold(0, |syndrome, i| syndrome ^ i)
+}
+
+/// Bits of `bytes`, most significant first
+pub(crate) fn unpack(bytes: &[u8]) -> impl Iterator<Item = u8> + '_ {
+    bytes
+        .iter()
+        .flat_map(|byte| (0..8).rev().map(move |i| (byte >> i) & 1))
+}
+
+/// Bytes from bits, most significant first
+pub(crate) fn pack(bits: impl Iterator<Item = u8>) -> Vec<u8> {
+    let bits: Vec<u8> = bits.collect();
+    bits.chunks(8)
+        .map(|chunk| chunk.iter().fold(0, |byte, &bit| (byte << 1) | bit) << (8 - chunk.len()))
+        .collect()
+}
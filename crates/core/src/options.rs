@@ -0,0 +1,148 @@
+use crate::lsb::Layout;
+use crate::traversal::StegoKey;
+use crate::{Channels, Compression, Distortion};
+
+/// Options controlling how a payload is embedded
+#[derive(Debug, Clone, Default)]
+pub struct EncodeOptions {
+    pub(crate) compression: Compression,
+    pub(crate) error_correction: u8,
+    pub(crate) layout: Layout,
+    pub(crate) stego_key: Option<StegoKey>,
+    pub(crate) lsb_matching: bool,
+    pub(crate) matrix_embedding: bool,
+    pub(crate) adaptive: bool,
+    pub(crate) trellis: Option<Distortion>,
+}
+
+impl EncodeOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Compress the payload before embedding it
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Protect the embedded data with Reed-Solomon error correction
+    ///
+    /// Every block of up to 255 bytes carries `parity` extra bytes and can
+    /// repair up to `parity / 2` corrupted bytes. `0` disables error
+    /// correction, values of 255 and above are rejected when encoding.
+    pub fn with_error_correction(mut self, parity: u8) -> Self {
+        self.error_correction = parity;
+        self
+    }
+
+    /// Store `bits` bits in the low end of every channel instead of one
+    ///
+    /// More bits raise capacity proportionally but make the changes easier
+    /// to see and to detect. The setting is recorded in the payload header so
+    /// decoding picks it up automatically. Up to 4 bits are accepted for
+    /// 8-bit images and up to 8 for 16-bit images; other values are rejected
+    /// when encoding.
+    pub fn with_bits_per_channel(mut self, bits: u8) -> Self {
+        self.layout.bits_per_channel = bits;
+        self
+    }
+
+    /// Embed only into the selected color channels
+    ///
+    /// Defaults to [`Channels::RGB`]. Using fewer channels lowers capacity
+    /// but can be less perceptible, e.g. blue only; [`Channels::ALPHA`]
+    /// requires an image with an alpha channel. The selection is recorded in
+    /// the payload header so decoding picks it up automatically; the fixed
+    /// part of the header itself always goes into the low bit of the color
+    /// samples (the first 43 pixels without a stego key) so that it can be
+    /// found.
+    pub fn with_channels(mut self, channels: Channels) -> Self {
+        self.layout.channels = channels;
+        self
+    }
+
+    /// Scatter the payload across the image in an order derived from `key`
+    ///
+    /// Without a key the payload fills the image from the top left corner.
+    /// With one, every channel slot is visited in a pseudo-random order
+    /// seeded by the key, so the changes are spread over the whole image and
+    /// the payload can only be found with [`DecodeOptions::with_stego_key`]
+    /// and the same key.
+    pub fn with_stego_key(mut self, key: &[u8]) -> Self {
+        self.stego_key = Some(StegoKey::new(key));
+        self
+    }
+
+    /// Use LSB matching instead of LSB replacement
+    ///
+    /// Replacing the low bit pairs up sample values (2n and 2n + 1), which
+    /// the chi-square attack detects. With matching a sample whose payload
+    /// bits differ is instead moved up or down at random to the nearest
+    /// value carrying the right bits, staying within the sample range. The
+    /// payload bits are the same either way, so decoding is unchanged.
+    pub fn with_lsb_matching(mut self, enabled: bool) -> Self {
+        self.lsb_matching = enabled;
+        self
+    }
+
+    /// Use matrix embedding (Hamming codes) to change fewer samples
+    ///
+    /// The payload is embedded `k` bits at a time into blocks of `2^k - 1`
+    /// cover bits, changing at most one of them per block. `k` is chosen as
+    /// large as the image allows, so short payloads in large images change
+    /// far fewer samples than plain LSB embedding. The guarantee counts cover
+    /// bits rather than samples: with more than one bit per channel the
+    /// flipped bit may be a higher one, changing its sample by more than 1. The code is
+    /// recorded in the payload header so decoding picks it up automatically.
+    /// Cannot be combined with error correction.
+    pub fn with_matrix_embedding(mut self, enabled: bool) -> Self {
+        self.matrix_embedding = enabled;
+        self
+    }
+
+    /// Prefer textured regions of the image over flat ones
+    ///
+    /// The payload body goes into the samples with the most local variance
+    /// first, leaving smooth areas such as sky untouched when the payload is
+    /// small. Variance is computed from the bits above the payload bits, so
+    /// the decoder finds the same samples without any extra input. Cannot be
+    /// combined with LSB matching, which may change those bits, or with
+    /// error correction.
+    pub fn with_adaptive_embedding(mut self, enabled: bool) -> Self {
+        self.adaptive = enabled;
+        self
+    }
+
+    /// Use syndrome-trellis codes to place the changes where they cost least
+    ///
+    /// Like matrix embedding the payload is coded into more payload bits than
+    /// it needs, here `w` bits for each message bit with `w` as large as the
+    /// image allows, but a Viterbi search picks the changes with the lowest
+    /// total cost under `distortion`. With [`Distortion::Uniform`] that is
+    /// simply the fewest changes. The code is recorded in the payload header
+    /// so decoding picks it up automatically. Cannot be combined with matrix
+    /// embedding or error correction.
+    pub fn with_syndrome_trellis(mut self, distortion: Distortion) -> Self {
+        self.trellis = Some(distortion);
+        self
+    }
+}
+
+/// Options controlling how a payload is located when decoding
+#[derive(Debug, Clone, Default)]
+pub struct DecodeOptions {
+    pub(crate) stego_key: Option<StegoKey>,
+}
+
+impl DecodeOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read a payload embedded with [`EncodeOptions::with_stego_key`]
+    pub fn with_stego_key(mut self, key: &[u8]) -> Self {
+        self.stego_key = Some(StegoKey::new(key));
+        self
+    }
+}
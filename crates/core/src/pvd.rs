@@ -0,0 +1,211 @@
+use image::{DynamicImage, GenericImageView};
+
+use crate::lsb::{self, Layout, Samples, SamplesMut};
+use crate::matrix::pack;
+use crate::{Image, ImgSteganoError};
+
+/// Marks an image carrying a pixel value differencing payload
+const MAGIC: [u8; 4] = *b"ISPV";
+
+/// Magic, payload length and payload checksum
+const PVD_HEADER_SIZE: usize = 4 + 4 + 4;
+
+/// Hide a payload with pixel value differencing (PVD)
+///
+/// Horizontally adjacent pixels are paired up in every color channel, and
+/// each pair stores bits in the difference between its two samples: the
+/// larger the difference, the more bits (3 for smooth pairs up to 7 for
+/// edges in 8-bit images), since changes are harder to see where the image
+/// already varies. The new difference stays in the same range as the old
+/// one and the pair's mean is kept, so changes are spread evenly over both
+/// samples. Capacity depends on the content, see [`Image::pvd_capacity`].
+pub fn encode_pvd(Image(input_image): Image, payload: &[u8]) -> Result<Image, ImgSteganoError> {
+    // Validate payload is not empty
+    if payload.is_empty() {
+        return Err(ImgSteganoError::EmptyMessage);
+    }
+    let mut image = input_image;
+    lsb::normalize(&mut image);
+    let available = capacity(&image);
+    if payload.len() > available {
+        return Err(ImgSteganoError::MessageTooLarge {
+            required: payload.len(),
+            available,
+        });
+    }
+
+    let mut stream = MAGIC.to_vec();
+    stream.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    stream.extend_from_slice(&crc32fast::hash(payload).to_be_bytes());
+    stream.extend_from_slice(payload);
+    let mut bits = stream
+        .iter()
+        .flat_map(|byte| (0..8).rev().map(move |i| (byte >> i) & 1))
+        .peekable();
+
+    let ranges = Ranges::new(&image);
+    let pairs = pairs(&image);
+    let mut samples = SamplesMut::new(&mut image);
+    for (first, second) in pairs {
+        if bits.peek().is_none() {
+            break;
+        }
+        let pair = Pair::new(samples.get(first), samples.get(second));
+        let Some((lower, bits_per_pair)) = ranges.usable(pair) else {
+            continue;
+        };
+        // a short last chunk is padded with zero bits
+        let value =
+            (0..bits_per_pair).fold(0, |value, _| (value << 1) | bits.next().unwrap_or(0) as i32);
+        let (a, b) = pair.with_difference(lower + value);
+        samples.set(first, a);
+        samples.set(second, b);
+    }
+    Ok(image.into())
+}
+
+/// Decode a payload previously hidden with [`encode_pvd`]
+///
+/// Returns [`ImgSteganoError::NoPayloadFound`] if the image carries no PVD
+/// payload, and [`ImgSteganoError::ChecksumMismatch`] if it was altered
+/// after encoding.
+pub fn decode_pvd(Image(encoded_image): &Image) -> Result<Vec<u8>, ImgSteganoError> {
+    let ranges = Ranges::new(encoded_image);
+    let samples = Samples::new(encoded_image);
+    let mut bits = pairs(encoded_image).flat_map(|(first, second)| {
+        let pair = Pair::new(samples.get(first), samples.get(second));
+        let (lower, bits_per_pair) = ranges.usable(pair).unwrap_or_default();
+        let value = pair.difference.abs() - lower;
+        (0..bits_per_pair)
+            .rev()
+            .map(move |i| ((value >> i) & 1) as u8)
+    });
+
+    let header = read_bytes(&mut bits, PVD_HEADER_SIZE);
+    if header.len() < PVD_HEADER_SIZE || !header.starts_with(&MAGIC) {
+        return Err(ImgSteganoError::NoPayloadFound);
+    }
+    let length = u32::from_be_bytes([header[4], header[5], header[6], header[7]]) as usize;
+    let expected = u32::from_be_bytes([header[8], header[9], header[10], header[11]]);
+    // only the pairs holding the payload are read
+    let payload = read_bytes(&mut bits, length);
+    if payload.len() < length {
+        return Err(ImgSteganoError::InvalidLength {
+            length,
+            available: payload.len(),
+        });
+    }
+    let actual = crc32fast::hash(&payload);
+    if actual != expected {
+        return Err(ImgSteganoError::ChecksumMismatch { expected, actual });
+    }
+    Ok(payload)
+}
+
+/// Number of payload bytes [`encode_pvd`] can hide in `image`
+pub(crate) fn capacity(image: &DynamicImage) -> usize {
+    let ranges = Ranges::new(image);
+    let samples = Samples::new(image);
+    let bits: usize = pairs(image)
+        .filter_map(|(first, second)| {
+            ranges.usable(Pair::new(samples.get(first), samples.get(second)))
+        })
+        .map(|(_, bits)| bits as usize)
+        .sum();
+    (bits / 8).saturating_sub(PVD_HEADER_SIZE)
+}
+
+/// Sample indices of every pair: horizontally adjacent pixels in the same
+/// color channel, row by row
+fn pairs(image: &DynamicImage) -> impl Iterator<Item = (usize, usize)> {
+    let color = image.color();
+    let samples_per_pixel = color.channel_count() as usize;
+    let channels = Layout::default().sample_indices(color);
+    let (width, height) = image.dimensions();
+    let (width, height) = (width as usize, height as usize);
+    let pairs_per_row = width / 2;
+    (0..height * pairs_per_row * channels.len()).map(move |i| {
+        let pair = i / channels.len();
+        let pixel = pair / pairs_per_row * width + pair % pairs_per_row * 2;
+        let first = pixel * samples_per_pixel + channels[i % channels.len()];
+        (first, first + samples_per_pixel)
+    })
+}
+
+/// Up to `size` whole bytes from `bits`, fewer if they run out
+fn read_bytes(bits: &mut impl Iterator<Item = u8>, size: usize) -> Vec<u8> {
+    let mut bits: Vec<u8> = bits.take(size.saturating_mul(8)).collect();
+    bits.truncate(bits.len() / 8 * 8);
+    pack(bits.into_iter())
+}
+
+/// Two samples, described by the difference between them and their mean
+///
+/// Changing the difference keeps `center`, the mean rounded down, so the
+/// decoder sees the same center whatever was embedded.
+#[derive(Clone, Copy)]
+struct Pair {
+    difference: i32,
+    center: i32,
+}
+
+impl Pair {
+    fn new(first: u16, second: u16) -> Self {
+        let (first, second) = (first as i32, second as i32);
+        Pair {
+            difference: second - first,
+            center: (first + second) >> 1,
+        }
+    }
+
+    /// The samples with the same center and sign of difference but a
+    /// difference of `magnitude`
+    fn with_difference(self, magnitude: i32) -> (u16, u16) {
+        let (first, second) = self.place(magnitude);
+        (first as u16, second as u16)
+    }
+
+    fn place(self, magnitude: i32) -> (i32, i32) {
+        let difference = if self.difference < 0 {
+            -magnitude
+        } else {
+            magnitude
+        };
+        let sum = self.center * 2 + (magnitude & 1);
+        ((sum - difference) / 2, (sum + difference) / 2)
+    }
+}
+
+/// Ranges of differences, each storing as many bits as its width allows
+///
+/// For 8-bit images the ranges are 0-7, 8-15, 16-31, 32-63, 64-127 and
+/// 128-255, storing 3, 3, 4, 5, 6 and 7 bits.
+struct Ranges {
+    max: i32,
+}
+
+impl Ranges {
+    fn new(image: &DynamicImage) -> Self {
+        // floating point samples are read as 16-bit ones
+        let depth = (2 * lsb::max_bits_per_channel(image.color()) as u32).min(16);
+        Ranges {
+            max: (1 << depth) - 1,
+        }
+    }
+
+    /// Lower end of the range of `pair` and the bits it stores, or `None`
+    /// if the largest difference in that range would leave the sample range
+    fn usable(&self, pair: Pair) -> Option<(i32, u32)> {
+        let magnitude = pair.difference.abs();
+        let (lower, bits) = match magnitude {
+            0..=7 => (0, 3),
+            _ => {
+                let bits = magnitude.ilog2();
+                (1 << bits, bits)
+            }
+        };
+        let (first, second) = pair.place(lower + (1 << bits) - 1);
+        let within = |sample: i32| (0..=self.max).contains(&sample);
+        (within(first) && within(second)).then_some((lower, bits))
+    }
+}
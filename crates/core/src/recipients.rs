@@ -0,0 +1,172 @@
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use hkdf::Hkdf;
+use sha2::Sha256;
+use x25519_dalek::{EphemeralSecret, StaticSecret};
+
+use crate::compress::decompress;
+use crate::decode::extract;
+use crate::encode::embed;
+use crate::header::{Header, Recipients, Stanza, KEY_SIZE};
+use crate::{EncodeOptions, Image, ImgSteganoError};
+
+/// Domain separation label for deriving the key that wraps the file key
+const WRAP_INFO: &[u8] = b"img_stegano/x25519";
+
+/// X25519 public key of a payload recipient
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecipientPublicKey(x25519_dalek::PublicKey);
+
+impl RecipientPublicKey {
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        RecipientPublicKey(bytes.into())
+    }
+
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.0.to_bytes()
+    }
+}
+
+/// X25519 secret key used to decode payloads addressed to its public key
+#[derive(Clone)]
+pub struct RecipientSecretKey(StaticSecret);
+
+impl RecipientSecretKey {
+    /// Generate a new random secret key
+    pub fn generate() -> Self {
+        RecipientSecretKey(StaticSecret::random_from_rng(OsRng))
+    }
+
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        RecipientSecretKey(bytes.into())
+    }
+
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.0.to_bytes()
+    }
+
+    /// The public key to share with anyone encoding payloads for this key
+    pub fn public_key(&self) -> RecipientPublicKey {
+        RecipientPublicKey((&self.0).into())
+    }
+}
+
+/// Generate a new recipient key pair
+pub fn generate_recipient_keypair() -> (RecipientSecretKey, RecipientPublicKey) {
+    let secret = RecipientSecretKey::generate();
+    let public = secret.public_key();
+    (secret, public)
+}
+
+/// Encrypt a payload to one or more recipients and encode it into an image
+///
+/// A random file key seals the payload with ChaCha20-Poly1305. For every
+/// recipient the file key is wrapped with a key agreed between a fresh
+/// ephemeral X25519 key and the recipient's public key, and stored as a
+/// stanza in the payload header.
+pub fn encode_for_recipients(
+    Image(input_image): Image,
+    payload: &[u8],
+    recipients: &[RecipientPublicKey],
+) -> Result<Image, ImgSteganoError> {
+    // Validate payload is not empty
+    if payload.is_empty() {
+        return Err(ImgSteganoError::EmptyMessage);
+    }
+    if recipients.is_empty() || recipients.len() > u8::MAX as usize {
+        return Err(ImgSteganoError::InvalidRecipientCount {
+            count: recipients.len(),
+        });
+    }
+
+    let mut file_key = Key::default();
+    OsRng.fill_bytes(&mut file_key);
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = ChaCha20Poly1305::new(&file_key)
+        .encrypt(&nonce, payload)
+        .map_err(|_| ImgSteganoError::EncryptionFailed)?;
+
+    let stanzas = recipients
+        .iter()
+        .map(|recipient| {
+            let ephemeral_secret = EphemeralSecret::random_from_rng(OsRng);
+            let ephemeral = x25519_dalek::PublicKey::from(&ephemeral_secret);
+            let shared = ephemeral_secret.diffie_hellman(&recipient.0);
+            // a low-order recipient key gives away the shared secret
+            if !shared.was_contributory() {
+                return Err(ImgSteganoError::InvalidPublicKey);
+            }
+            let wrap_key = derive_wrap_key(shared.as_bytes(), ephemeral.as_bytes(), &recipient.0);
+            let wrapped_key = ChaCha20Poly1305::new(&wrap_key)
+                .encrypt(&Nonce::default(), file_key.as_slice())
+                .map_err(|_| ImgSteganoError::EncryptionFailed)?;
+            Ok(Stanza {
+                ephemeral: ephemeral.to_bytes(),
+                wrapped_key: wrapped_key
+                    .try_into()
+                    .map_err(|_| ImgSteganoError::EncryptionFailed)?,
+            })
+        })
+        .collect::<Result<Vec<_>, ImgSteganoError>>()?;
+
+    let header = Header::new(&ciphertext).with_recipients(Recipients {
+        stanzas,
+        nonce: nonce.into(),
+    });
+    embed(input_image, header, &ciphertext, &EncodeOptions::default())
+}
+
+/// Decode a payload hidden with [`encode_for_recipients`] using a recipient's secret key
+///
+/// Returns [`ImgSteganoError::NotARecipient`] if no stanza can be unwrapped
+/// with the key, and [`ImgSteganoError::InvalidPublicKey`] if a stanza's
+/// ephemeral key is a low-order point.
+pub fn decode_with_key(
+    encoded_image: &Image,
+    secret_key: &RecipientSecretKey,
+) -> Result<Vec<u8>, ImgSteganoError> {
+    let (header, ciphertext, _) = extract(encoded_image)?;
+    let Some(recipients) = &header.recipients else {
+        return Err(ImgSteganoError::NotARecipient);
+    };
+
+    let public_key = secret_key.public_key();
+    let mut file_key = None;
+    for stanza in &recipients.stanzas {
+        let shared = secret_key.0.diffie_hellman(&stanza.ephemeral.into());
+        if !shared.was_contributory() {
+            return Err(ImgSteganoError::InvalidPublicKey);
+        }
+        let wrap_key = derive_wrap_key(shared.as_bytes(), &stanza.ephemeral, &public_key.0);
+        file_key = ChaCha20Poly1305::new(&wrap_key)
+            .decrypt(&Nonce::default(), stanza.wrapped_key.as_slice())
+            .ok();
+        if file_key.is_some() {
+            break;
+        }
+    }
+    let file_key = file_key.ok_or(ImgSteganoError::NotARecipient)?;
+
+    let plaintext = ChaCha20Poly1305::new(Key::from_slice(&file_key))
+        .decrypt(Nonce::from_slice(&recipients.nonce), ciphertext.as_slice())
+        .map_err(|_| ImgSteganoError::WrongPasswordOrCorrupt)?;
+    decompress(&header, plaintext)
+}
+
+/// Derive the key wrapping the file key for one recipient
+fn derive_wrap_key(
+    shared: &[u8; KEY_SIZE],
+    ephemeral: &[u8; KEY_SIZE],
+    recipient: &x25519_dalek::PublicKey,
+) -> Key {
+    let mut salt = [0u8; KEY_SIZE * 2];
+    salt[..KEY_SIZE].copy_from_slice(ephemeral);
+    salt[KEY_SIZE..].copy_from_slice(recipient.as_bytes());
+
+    let mut wrap_key = Key::default();
+    Hkdf::<Sha256>::new(Some(&salt), shared)
+        .expand(WRAP_INFO, &mut wrap_key)
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+    wrap_key
+}
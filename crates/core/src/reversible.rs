@@ -0,0 +1,300 @@
+use image::DynamicImage;
+
+use crate::lsb::{self, Layout, Samples, SamplesMut};
+use crate::matrix::pack;
+use crate::{Image, ImgSteganoError};
+
+/// Marks an image carrying a reversibly embedded payload
+const MAGIC: [u8; 4] = *b"ISRV";
+
+/// Magic, peak and zero bin, stored in the low bits of the leading samples
+const RESERVED_SIZE: usize = 4 + 2 + 2;
+
+/// Leading samples whose low bits hold the reserved bytes
+const RESERVED_SAMPLES: usize = RESERVED_SIZE * 8;
+
+/// Payload length, checksum, original reserved bits and number of
+/// collisions, ahead of the collision positions and the payload
+const PREFIX_SIZE: usize = 4 + 4 + RESERVED_SIZE + 4;
+
+/// Position of the checksum in the stream, right after the payload length
+const CHECKSUM_RANGE: std::ops::Range<usize> = 4..8;
+
+/// Hide a payload so that the exact original image can be restored later
+///
+/// Uses histogram shifting: the most frequent sample value (the peak)
+/// carries one bit per occurrence, and the values between it and a rare
+/// value (the zero) are shifted by one to make room. Every change is
+/// undone by [`extract_and_restore`], which returns the cover bit for bit.
+/// Capacity depends on the content, see [`Image::reversible_capacity`].
+/// Only 8 and 16-bit images are supported and the result must be stored
+/// losslessly.
+pub fn encode_reversible(
+    Image(input_image): Image,
+    payload: &[u8],
+) -> Result<Image, ImgSteganoError> {
+    // Validate payload is not empty
+    if payload.is_empty() {
+        return Err(ImgSteganoError::EmptyMessage);
+    }
+    let mut image = input_image;
+    let (reserved, carriers) = split_slots(&image)?;
+    let plan = plan(&image, &carriers);
+    let available = plan.capacity();
+    if payload.len() > available {
+        return Err(ImgSteganoError::MessageTooLarge {
+            required: payload.len(),
+            available,
+        });
+    }
+
+    // original low bits of the reserved samples travel with the payload
+    let original: Vec<u8> = lsb::read_slots(&image, Layout::default(), reserved.iter().copied())
+        .take(RESERVED_SIZE)
+        .collect();
+    let mut stream = Vec::with_capacity(PREFIX_SIZE + plan.collisions.len() * 4 + payload.len());
+    stream.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    stream.extend_from_slice(&[0; 4]);
+    stream.extend_from_slice(&original);
+    stream.extend_from_slice(&(plan.collisions.len() as u32).to_be_bytes());
+    for &position in &plan.collisions {
+        stream.extend_from_slice(&position.to_be_bytes());
+    }
+    stream.extend_from_slice(payload);
+    let crc = checksum(&stream);
+    stream[CHECKSUM_RANGE].copy_from_slice(&crc.to_be_bytes());
+
+    let mut bits = stream
+        .iter()
+        .flat_map(|byte| (0..8).rev().map(move |i| (byte >> i) & 1));
+    let mut samples = SamplesMut::new(&mut image);
+    let marked = plan.marked();
+    for &index in &carriers {
+        let value = samples.get(index);
+        if value == plan.peak {
+            if bits.next() == Some(1) {
+                samples.set(index, marked);
+            }
+        } else if between(value, plan.peak, plan.zero) {
+            samples.set(index, step(value, plan.peak, plan.zero));
+        }
+    }
+
+    let mut reserved_bytes = MAGIC.to_vec();
+    reserved_bytes.extend_from_slice(&plan.peak.to_be_bytes());
+    reserved_bytes.extend_from_slice(&plan.zero.to_be_bytes());
+    lsb::write_slots(
+        &mut image,
+        Layout::default(),
+        reserved.into_iter(),
+        false,
+        0,
+        &reserved_bytes,
+    );
+    Ok(image.into())
+}
+
+/// Recover a payload hidden with [`encode_reversible`] and the original image
+///
+/// Returns [`ImgSteganoError::NoPayloadFound`] if the image carries no
+/// reversible payload, and [`ImgSteganoError::ChecksumMismatch`] if it was
+/// altered after encoding, in which case the cover cannot be restored.
+pub fn extract_and_restore(
+    Image(encoded_image): &Image,
+) -> Result<(Vec<u8>, Image), ImgSteganoError> {
+    let mut image = encoded_image.clone();
+    let (reserved, carriers) = split_slots(&image).map_err(|_| ImgSteganoError::NoPayloadFound)?;
+    let reserved_bytes: Vec<u8> =
+        lsb::read_slots(&image, Layout::default(), reserved.iter().copied())
+            .take(RESERVED_SIZE)
+            .collect();
+    if !reserved_bytes.starts_with(&MAGIC) {
+        return Err(ImgSteganoError::NoPayloadFound);
+    }
+    let peak = u16::from_be_bytes([reserved_bytes[4], reserved_bytes[5]]);
+    let zero = u16::from_be_bytes([reserved_bytes[6], reserved_bytes[7]]);
+    if peak == zero || peak.max(zero) > max_value(&image) {
+        return Err(ImgSteganoError::NoPayloadFound);
+    }
+    let marked = step(peak, peak, zero);
+
+    let mut samples = SamplesMut::new(&mut image);
+    let bits = carriers
+        .iter()
+        .filter_map(|&index| match samples.get(index) {
+            value if value == peak => Some(0),
+            value if value == marked => Some(1),
+            _ => None,
+        });
+    let packed = pack(bits);
+    let mut stream = packed.as_slice();
+
+    let length = read_u32(&mut stream)? as usize;
+    let expected = read_u32(&mut stream)?;
+    let original = take(&mut stream, RESERVED_SIZE)?.to_vec();
+    let count = read_u32(&mut stream)? as usize;
+    if count > stream.len() / 4 {
+        return Err(ImgSteganoError::TruncatedPayload);
+    }
+    let collisions: Vec<usize> = (0..count)
+        .map(|_| read_u32(&mut stream).map(|position| position as usize))
+        .collect::<Result<_, _>>()?;
+    let payload = take(&mut stream, length)?.to_vec();
+    let actual = checksum(&packed[..packed.len() - stream.len()]);
+    if actual != expected {
+        return Err(ImgSteganoError::ChecksumMismatch { expected, actual });
+    }
+
+    for &index in &carriers {
+        let value = samples.get(index);
+        if value == marked {
+            samples.set(index, peak);
+        } else if between(value, marked, zero) || value == zero {
+            samples.set(index, step(value, zero, peak));
+        }
+    }
+    for position in collisions {
+        let index = *carriers
+            .get(position)
+            .ok_or(ImgSteganoError::TruncatedPayload)?;
+        samples.set(index, zero);
+    }
+    lsb::write_slots(
+        &mut image,
+        Layout::default(),
+        reserved.into_iter(),
+        false,
+        0,
+        &original,
+    );
+    Ok((payload, image.into()))
+}
+
+/// Number of payload bytes [`encode_reversible`] can hide in `image`
+pub(crate) fn capacity(image: &DynamicImage) -> usize {
+    match split_slots(image) {
+        Ok((_, carriers)) => plan(image, &carriers).capacity(),
+        Err(_) => 0,
+    }
+}
+
+/// Peak and zero bin chosen for an image
+struct Plan {
+    peak: u16,
+    zero: u16,
+    /// Occurrences of the peak, one payload bit each
+    peak_count: usize,
+    /// Carriers holding the zero bin value, restored from their positions
+    collisions: Vec<u32>,
+}
+
+impl Plan {
+    /// Value a peak sample takes to carry a one bit, next to the peak
+    /// towards the zero
+    fn marked(&self) -> u16 {
+        step(self.peak, self.peak, self.zero)
+    }
+
+    fn capacity(&self) -> usize {
+        (self.peak_count / 8).saturating_sub(PREFIX_SIZE + self.collisions.len() * 4)
+    }
+}
+
+/// Pick the peak and zero bin from the histogram of the carrier samples
+///
+/// The zero is the least frequent value, nearest to the peak among equally
+/// rare ones so the fewest samples shift. A value next to the peak can only
+/// be the zero when it does not occur at all, since its samples would
+/// otherwise read as payload bits.
+fn plan(image: &DynamicImage, carriers: &[usize]) -> Plan {
+    let samples = Samples::new(image);
+    let max = max_value(image);
+    let mut histogram = vec![0usize; max as usize + 1];
+    for &index in carriers {
+        histogram[samples.get(index) as usize] += 1;
+    }
+
+    let peak = (0..=max)
+        .max_by_key(|&value| (histogram[value as usize], std::cmp::Reverse(value)))
+        .unwrap_or_default();
+    let zero = (0..=max)
+        .filter(|&value| value != peak)
+        .filter(|&value| value.abs_diff(peak) > 1 || histogram[value as usize] == 0)
+        .min_by_key(|&value| (histogram[value as usize], value.abs_diff(peak)))
+        .expect("samples span at least 256 values");
+    let collisions = carriers
+        .iter()
+        .enumerate()
+        .filter(|&(_, &index)| samples.get(index) == zero)
+        .map(|(position, _)| position as u32)
+        .collect();
+    Plan {
+        peak,
+        zero,
+        peak_count: histogram[peak as usize],
+        collisions,
+    }
+}
+
+/// Split the color samples into the reserved leading ones and the carriers
+fn split_slots(image: &DynamicImage) -> Result<(Vec<usize>, Vec<usize>), ImgSteganoError> {
+    if matches!(
+        image,
+        DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_)
+    ) {
+        return Err(ImgSteganoError::InvalidOptions {
+            reason: "reversible embedding needs an 8 or 16-bit image",
+        });
+    }
+    let mut slots: Vec<usize> = lsb::slots(image, Layout::default(), None).collect();
+    if slots.len() <= RESERVED_SAMPLES {
+        return Err(ImgSteganoError::MessageTooLarge {
+            required: 1,
+            available: 0,
+        });
+    }
+    let carriers = slots.split_off(RESERVED_SAMPLES);
+    Ok((slots, carriers))
+}
+
+fn max_value(image: &DynamicImage) -> u16 {
+    u16::MAX >> (16 - 2 * lsb::max_bits_per_channel(image.color()))
+}
+
+/// Whether `value` lies strictly between `a` and `b`
+fn between(value: u16, a: u16, b: u16) -> bool {
+    a.min(b) < value && value < a.max(b)
+}
+
+/// `value` moved by one from `from` towards `to`
+fn step(value: u16, from: u16, to: u16) -> u16 {
+    if to > from {
+        value + 1
+    } else {
+        value - 1
+    }
+}
+
+/// CRC-32 of everything in `stream` but the checksum itself, so restoring
+/// fails if the original bits or collisions were altered, not just the
+/// payload
+fn checksum(stream: &[u8]) -> u32 {
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(&stream[..CHECKSUM_RANGE.start]);
+    hasher.update(&stream[CHECKSUM_RANGE.end..]);
+    hasher.finalize()
+}
+
+fn read_u32(stream: &mut &[u8]) -> Result<u32, ImgSteganoError> {
+    let bytes = take(stream, 4)?;
+    Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn take<'a>(stream: &mut &'a [u8], size: usize) -> Result<&'a [u8], ImgSteganoError> {
+    if stream.len() < size {
+        return Err(ImgSteganoError::TruncatedPayload);
+    }
+    let (bytes, rest) = stream.split_at(size);
+    *stream = rest;
+    Ok(bytes)
+}
@@ -0,0 +1,199 @@
+use rand_core::{OsRng, RngCore};
+
+use crate::decode::{ensure_plaintext, extract};
+use crate::encode::embed;
+use crate::gf256::{self, poly};
+use crate::header::{Header, Metadata};
+use crate::{EncodeOptions, Image, ImgSteganoError};
+
+/// Metadata kind of one share of a threshold-shared payload
+pub(crate) const KIND_SHARE: u8 = 4;
+
+/// Set ID, threshold, share count and x coordinate
+const SHARE_RECORD_SIZE: usize = 8 + 1 + 1 + 1;
+
+/// One point on every byte's sharing polynomial
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Share {
+    set_id: [u8; 8],
+    threshold: u8,
+    count: u8,
+    /// Evaluation point of this share, never zero since zero holds the secret
+    x: u8,
+}
+
+impl Share {
+    fn record(&self) -> Vec<u8> {
+        let mut record = Vec::with_capacity(SHARE_RECORD_SIZE);
+        record.extend_from_slice(&self.set_id);
+        record.extend_from_slice(&[self.threshold, self.count, self.x]);
+        record
+    }
+
+    fn from_header(header: &Header) -> Result<Self, ImgSteganoError> {
+        let record = match &header.metadata {
+            Some(metadata) if metadata.kind == KIND_SHARE => &metadata.record,
+            _ => return Err(ImgSteganoError::NotAShare),
+        };
+        let record: &[u8; SHARE_RECORD_SIZE] =
+            record
+                .as_slice()
+                .try_into()
+                .map_err(|_| ImgSteganoError::InvalidShare {
+                    reason: "share record is malformed",
+                })?;
+
+        let share = Share {
+            set_id: record[0..8].try_into().unwrap(),
+            threshold: record[8],
+            count: record[9],
+            x: record[10],
+        };
+        if share.threshold == 0 || share.threshold > share.count {
+            return Err(ImgSteganoError::InvalidShare {
+                reason: "share threshold is out of range",
+            });
+        }
+        if share.x == 0 || share.x > share.count {
+            return Err(ImgSteganoError::InvalidShare {
+                reason: "share index is out of range",
+            });
+        }
+        Ok(share)
+    }
+}
+
+/// Hide a secret in several images so that any `threshold` of them recover it
+///
+/// Uses Shamir secret sharing over GF(256): every byte of the payload is the
+/// constant term of a random polynomial of degree `threshold - 1`, and each
+/// cover stores that polynomial evaluated at a different point. Any
+/// `threshold` images reconstruct the payload with [`recover_from_images`];
+/// fewer reveal nothing about it beyond its length.
+pub fn encode_shares(
+    covers: Vec<Image>,
+    payload: &[u8],
+    threshold: u8,
+) -> Result<Vec<Image>, ImgSteganoError> {
+    // Validate payload is not empty
+    if payload.is_empty() {
+        return Err(ImgSteganoError::EmptyMessage);
+    }
+    if covers.len() > u8::MAX as usize {
+        return Err(ImgSteganoError::InvalidOptions {
+            reason: "secret sharing supports at most 255 cover images",
+        });
+    }
+    if threshold < 2 || threshold as usize > covers.len() {
+        return Err(ImgSteganoError::InvalidOptions {
+            reason: "threshold must be at least 2 and at most the number of covers",
+        });
+    }
+
+    let mut set_id = [0u8; 8];
+    OsRng.fill_bytes(&mut set_id);
+
+    // One random polynomial per payload byte, highest degree coefficient first
+    let mut coefficients = vec![0u8; payload.len() * (threshold as usize - 1)];
+    OsRng.fill_bytes(&mut coefficients);
+    let polynomials: Vec<Vec<u8>> = coefficients
+        .chunks(threshold as usize - 1)
+        .zip(payload)
+        .map(|(random, &secret)| [random, &[secret]].concat())
+        .collect();
+
+    let count = covers.len() as u8;
+    covers
+        .into_iter()
+        .zip(1..=count)
+        .map(|(Image(cover), x)| {
+            let body: Vec<u8> = polynomials
+                .iter()
+                .map(|polynomial| poly::eval(polynomial, x))
+                .collect();
+            let share = Share {
+                set_id,
+                threshold,
+                count,
+                x,
+            };
+            let metadata = Metadata {
+                kind: KIND_SHARE,
+                record: share.record(),
+            };
+            let header = Header::new(&body).with_metadata(metadata);
+            embed(cover, header, &body, &EncodeOptions::default())
+        })
+        .collect()
+}
+
+/// Recover a secret hidden with [`encode_shares`] from at least `threshold` images
+///
+/// The images may be given in any order. Returns
+/// [`ImgSteganoError::InsufficientShares`] if fewer distinct shares than the
+/// threshold are supplied. Any shares beyond the threshold are checked
+/// against the recovered polynomials, so a tampered share is reported as
+/// [`ImgSteganoError::InvalidShare`] when enough images are available.
+pub fn recover_from_images(images: &[Image]) -> Result<Vec<u8>, ImgSteganoError> {
+    let mut shares: Vec<(Share, Vec<u8>)> = Vec::with_capacity(images.len());
+    for image in images {
+        let (header, body, _) = extract(image)?;
+        ensure_plaintext(&header)?;
+        shares.push((Share::from_header(&header)?, body));
+    }
+    let (first, first_body) = shares.first().ok_or(ImgSteganoError::NoPayloadFound)?;
+    let (first, length) = (*first, first_body.len());
+    if shares.iter().any(|(share, body)| {
+        (share.set_id, share.threshold, share.count, body.len())
+            != (first.set_id, first.threshold, first.count, length)
+    }) {
+        return Err(ImgSteganoError::ShareSetMismatch);
+    }
+
+    // The same image passed twice is harmless
+    shares.sort_by_key(|(share, _)| share.x);
+    shares.dedup_by_key(|(share, _)| share.x);
+    if shares.len() < first.threshold as usize {
+        return Err(ImgSteganoError::InsufficientShares {
+            available: shares.len(),
+            threshold: first.threshold,
+        });
+    }
+
+    let (basis, extra) = shares.split_at(first.threshold as usize);
+    let xs: Vec<u8> = basis.iter().map(|(share, _)| share.x).collect();
+    let interpolate = |x: u8| {
+        let weights = lagrange_weights(&xs, x);
+        (0..length)
+            .map(|i| {
+                basis
+                    .iter()
+                    .zip(&weights)
+                    .fold(0, |sum, ((_, body), &weight)| {
+                        sum ^ gf256::mul(weight, body[i])
+                    })
+            })
+            .collect::<Vec<u8>>()
+    };
+
+    for (share, body) in extra {
+        if interpolate(share.x) != *body {
+            return Err(ImgSteganoError::InvalidShare {
+                reason: "shares are inconsistent",
+            });
+        }
+    }
+    Ok(interpolate(0))
+}
+
+/// Lagrange basis polynomials for the points `xs`, evaluated at `x`
+fn lagrange_weights(xs: &[u8], x: u8) -> Vec<u8> {
+    // Addition and subtraction are both XOR in GF(256)
+    xs.iter()
+        .map(|&xi| {
+            xs.iter().filter(|&&xj| xj != xi).fold(1, |weight, &xj| {
+                gf256::mul(weight, gf256::div(x ^ xj, xi ^ xj))
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,142 @@
+use ed25519_dalek::{Signer, SigningKey, VerifyingKey};
+use rand_core::OsRng;
+
+use crate::compress::{compress, decompress};
+use crate::decode::{ensure_plaintext, extract_with_options};
+use crate::encode::embed_signed;
+use crate::header::{Header, Signature, SIGNATURE_SIZE};
+use crate::{DecodeOptions, EncodeOptions, Image, ImgSteganoError};
+
+/// Domain separation label prepended to the signed payload
+const SIGN_CONTEXT: &[u8] = b"img_stegano/ed25519";
+
+/// Ed25519 public key identifying who signed a payload
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SignerPublicKey(VerifyingKey);
+
+impl SignerPublicKey {
+    pub fn from_bytes(bytes: [u8; 32]) -> Result<Self, ImgSteganoError> {
+        VerifyingKey::from_bytes(&bytes)
+            .map(SignerPublicKey)
+            .map_err(|_| ImgSteganoError::InvalidPublicKey)
+    }
+
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.0.to_bytes()
+    }
+}
+
+/// Ed25519 secret key used to sign payloads on encode
+#[derive(Clone)]
+pub struct SignerSecretKey(SigningKey);
+
+impl SignerSecretKey {
+    /// Generate a new random secret key
+    pub fn generate() -> Self {
+        SignerSecretKey(SigningKey::generate(&mut OsRng))
+    }
+
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        SignerSecretKey(SigningKey::from_bytes(&bytes))
+    }
+
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.0.to_bytes()
+    }
+
+    /// The public key to give to anyone verifying payloads signed with this key
+    pub fn public_key(&self) -> SignerPublicKey {
+        SignerPublicKey(self.0.verifying_key())
+    }
+}
+
+/// Generate a new signer key pair
+pub fn generate_signer_keypair() -> (SignerSecretKey, SignerPublicKey) {
+    let secret = SignerSecretKey::generate();
+    let public = secret.public_key();
+    (secret, public)
+}
+
+/// Sign a payload and encode it into an image
+///
+/// The signer's public key and an Ed25519 signature over the payload and
+/// every other header section are stored in the payload header. The payload itself stays readable with
+/// [`decode_bytes`](crate::decode_bytes).
+pub fn encode_signed(
+    input_image: Image,
+    payload: &[u8],
+    secret_key: &SignerSecretKey,
+) -> Result<Image, ImgSteganoError> {
+    encode_signed_with_options(input_image, payload, secret_key, &EncodeOptions::default())
+}
+
+/// Sign a payload and encode it into an image with the given options
+///
+/// With compression the signature covers the compressed payload, as it is
+/// stored in the image, along with the compression section.
+pub fn encode_signed_with_options(
+    Image(input_image): Image,
+    payload: &[u8],
+    secret_key: &SignerSecretKey,
+    options: &EncodeOptions,
+) -> Result<Image, ImgSteganoError> {
+    // Validate payload is not empty
+    if payload.is_empty() {
+        return Err(ImgSteganoError::EmptyMessage);
+    }
+
+    let (header, body) = compress(payload, options.compression)?;
+    let signer = secret_key.public_key().to_bytes();
+    // the signature is filled in once the rest of the header is final
+    let header = header.with_signature(Signature {
+        signer,
+        signature: [0; SIGNATURE_SIZE],
+    });
+    let sign = |header: &Header, body: &[u8]| Signature {
+        signer,
+        signature: secret_key.0.sign(&signed_message(header, body)).to_bytes(),
+    };
+    embed_signed(input_image, header, &body, options, &sign)
+}
+
+/// Decode a signed payload and verify it was signed by one of the trusted keys
+///
+/// Returns the payload together with the key that signed it.
+pub fn decode_and_verify(
+    encoded_image: &Image,
+    trusted: &[SignerPublicKey],
+) -> Result<(Vec<u8>, SignerPublicKey), ImgSteganoError> {
+    decode_and_verify_with_options(encoded_image, trusted, &DecodeOptions::default())
+}
+
+/// [`decode_and_verify`] for a payload embedded with a stego key
+pub fn decode_and_verify_with_options(
+    encoded_image: &Image,
+    trusted: &[SignerPublicKey],
+    options: &DecodeOptions,
+) -> Result<(Vec<u8>, SignerPublicKey), ImgSteganoError> {
+    let (header, body, _) = extract_with_options(encoded_image, options)?;
+    ensure_plaintext(&header)?;
+    let signature = header.signature.ok_or(ImgSteganoError::SignatureMissing)?;
+
+    let signer = SignerPublicKey::from_bytes(signature.signer)
+        .map_err(|_| ImgSteganoError::InvalidSignature)?;
+    signer
+        .0
+        .verify_strict(
+            &signed_message(&header, &body),
+            &ed25519_dalek::Signature::from_bytes(&signature.signature),
+        )
+        .map_err(|_| ImgSteganoError::InvalidSignature)?;
+
+    if !trusted.contains(&signer) {
+        return Err(ImgSteganoError::UntrustedSigner);
+    }
+    Ok((decompress(&header, body)?, signer))
+}
+
+/// Everything a signature covers: the header but the signature section,
+/// then the body as stored
+fn signed_message(header: &Header, body: &[u8]) -> Vec<u8> {
+    [SIGN_CONTEXT, &header.signed_bytes(), body].concat()
+}
@@ -0,0 +1,198 @@
+use rand_core::{OsRng, RngCore};
+
+use crate::decode::{ensure_plaintext, extract};
+use crate::encode::embed;
+use crate::header::{Header, Metadata};
+use crate::{EncodeOptions, Image, ImgSteganoError};
+
+/// Metadata kind of one shard of a payload split across several images
+pub(crate) const KIND_SHARD: u8 = 3;
+
+/// Set ID, index, count, payload length and payload checksum
+const SHARD_RECORD_SIZE: usize = 8 + 2 + 2 + 4 + 4;
+
+/// Bytes each shard spends on its metadata section
+const SHARD_OVERHEAD: usize = 1 + 2 + SHARD_RECORD_SIZE;
+
+/// Position of one image within a split payload
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Shard {
+    set_id: [u8; 8],
+    index: u16,
+    count: u16,
+    length: u32,
+    checksum: u32,
+}
+
+impl Shard {
+    fn record(&self) -> Vec<u8> {
+        let mut record = Vec::with_capacity(SHARD_RECORD_SIZE);
+        record.extend_from_slice(&self.set_id);
+        record.extend_from_slice(&self.index.to_be_bytes());
+        record.extend_from_slice(&self.count.to_be_bytes());
+        record.extend_from_slice(&self.length.to_be_bytes());
+        record.extend_from_slice(&self.checksum.to_be_bytes());
+        record
+    }
+
+    fn from_header(header: &Header) -> Result<Self, ImgSteganoError> {
+        let record = match &header.metadata {
+            Some(metadata) if metadata.kind == KIND_SHARD => &metadata.record,
+            _ => return Err(ImgSteganoError::NotAShard),
+        };
+        let record: &[u8; SHARD_RECORD_SIZE] =
+            record
+                .as_slice()
+                .try_into()
+                .map_err(|_| ImgSteganoError::InvalidShard {
+                    reason: "shard record is malformed",
+                })?;
+
+        let shard = Shard {
+            set_id: record[0..8].try_into().unwrap(),
+            index: u16::from_be_bytes([record[8], record[9]]),
+            count: u16::from_be_bytes([record[10], record[11]]),
+            length: u32::from_be_bytes([record[12], record[13], record[14], record[15]]),
+            checksum: u32::from_be_bytes([record[16], record[17], record[18], record[19]]),
+        };
+        if shard.index >= shard.count {
+            return Err(ImgSteganoError::InvalidShard {
+                reason: "shard index is out of range",
+            });
+        }
+        Ok(shard)
+    }
+}
+
+/// Split a payload across several cover images
+///
+/// Each cover receives a share of the payload proportional to its capacity,
+/// tagged with its sequence number and a random set ID shared by all shards.
+/// Pass the returned images to [`decode_join`] in any order to reassemble it.
+pub fn encode_split(covers: Vec<Image>, payload: &[u8]) -> Result<Vec<Image>, ImgSteganoError> {
+    // Validate payload is not empty
+    if payload.is_empty() {
+        return Err(ImgSteganoError::EmptyMessage);
+    }
+    if covers.is_empty() || covers.len() > u16::MAX as usize {
+        return Err(ImgSteganoError::InvalidOptions {
+            reason: "split needs between 1 and 65535 cover images",
+        });
+    }
+    let length = u32::try_from(payload.len()).map_err(|_| ImgSteganoError::MessageTooLarge {
+        required: payload.len(),
+        available: u32::MAX as usize,
+    })?;
+
+    let capacities: Vec<usize> = covers
+        .iter()
+        .map(|cover| cover.capacity().saturating_sub(SHARD_OVERHEAD))
+        .collect();
+    let sizes = shard_sizes(payload.len(), &capacities)?;
+
+    let mut set_id = [0u8; 8];
+    OsRng.fill_bytes(&mut set_id);
+    let checksum = crc32fast::hash(payload);
+
+    let mut remaining = payload;
+    covers
+        .into_iter()
+        .zip(sizes)
+        .enumerate()
+        .map(|(index, (Image(cover), size))| {
+            let (chunk, rest) = remaining.split_at(size);
+            remaining = rest;
+            let shard = Shard {
+                set_id,
+                index: index as u16,
+                count: capacities.len() as u16,
+                length,
+                checksum,
+            };
+            let metadata = Metadata {
+                kind: KIND_SHARD,
+                record: shard.record(),
+            };
+            let header = Header::new(chunk).with_metadata(metadata);
+            embed(cover, header, chunk, &EncodeOptions::default())
+        })
+        .collect()
+}
+
+/// Reassemble a payload split with [`encode_split`]
+///
+/// The images may be given in any order. Returns
+/// [`ImgSteganoError::MissingShards`] listing the sequence numbers that were
+/// not supplied, and [`ImgSteganoError::ShardSetMismatch`] if the images
+/// belong to different splits.
+pub fn decode_join(images: &[Image]) -> Result<Vec<u8>, ImgSteganoError> {
+    let mut shards: Vec<(Shard, Vec<u8>)> = Vec::with_capacity(images.len());
+    for image in images {
+        let (header, body, _) = extract(image)?;
+        ensure_plaintext(&header)?;
+        shards.push((Shard::from_header(&header)?, body));
+    }
+    let (first, _) = shards.first().ok_or(ImgSteganoError::NoPayloadFound)?;
+    let first = *first;
+    if shards.iter().any(|(shard, _)| {
+        (shard.set_id, shard.count, shard.length, shard.checksum)
+            != (first.set_id, first.count, first.length, first.checksum)
+    }) {
+        return Err(ImgSteganoError::ShardSetMismatch);
+    }
+
+    // The same image passed twice is harmless
+    shards.sort_by_key(|(shard, _)| shard.index);
+    shards.dedup_by_key(|(shard, _)| shard.index);
+
+    let missing: Vec<u16> = (0..first.count)
+        .filter(|index| {
+            shards
+                .binary_search_by_key(index, |(shard, _)| shard.index)
+                .is_err()
+        })
+        .collect();
+    if !missing.is_empty() {
+        return Err(ImgSteganoError::MissingShards {
+            missing,
+            count: first.count,
+        });
+    }
+
+    let payload: Vec<u8> = shards.into_iter().flat_map(|(_, body)| body).collect();
+    if payload.len() != first.length as usize {
+        return Err(ImgSteganoError::TruncatedPayload);
+    }
+    let actual = crc32fast::hash(&payload);
+    if actual != first.checksum {
+        return Err(ImgSteganoError::ChecksumMismatch {
+            expected: first.checksum,
+            actual,
+        });
+    }
+    Ok(payload)
+}
+
+/// Share `length` bytes out between covers in proportion to their capacity
+fn shard_sizes(length: usize, capacities: &[usize]) -> Result<Vec<usize>, ImgSteganoError> {
+    let total: usize = capacities.iter().sum();
+    if length > total {
+        return Err(ImgSteganoError::MessageTooLarge {
+            required: length,
+            available: total,
+        });
+    }
+
+    let mut sizes: Vec<usize> = capacities
+        .iter()
+        .map(|&capacity| (length as u128 * capacity as u128 / total as u128) as usize)
+        .collect();
+    // Rounding down leaves a few bytes over, hand them to covers with room
+    let mut leftover = length - sizes.iter().sum::<usize>();
+    for (size, &capacity) in sizes.iter_mut().zip(capacities) {
+        let extra = leftover.min(capacity - *size);
+        *size += extra;
+        leftover -= extra;
+    }
+    Ok(sizes)
+}
@@ -0,0 +1,254 @@
+use std::io::{self, Read, Write};
+
+use crate::compress::decompress;
+use crate::decode::{ensure_plaintext, extract_with_options, read_prefix};
+use crate::header::{Header, HEADER_SIZE};
+use crate::lsb::{Cursor, Layout};
+use crate::traversal::StegoKey;
+use crate::{lsb, Compression, DecodeOptions, EncodeOptions, Image, ImgSteganoError};
+
+/// Streams a payload into an image as it is written
+///
+/// Bytes go straight into the image LSBs behind a reserved header, so large
+/// payloads can be piped in (e.g. with [`std::io::copy`]) without buffering
+/// them. The header is written by [`StegoWriter::finish`]; until then the
+/// image carries no readable payload.
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use img_stegano::{Image, ImageFormat, StegoWriter};
+///
+/// let mut image = Image::open("cover.png")?;
+/// let mut writer = StegoWriter::new(&mut image);
+/// std::io::copy(&mut std::fs::File::open("secret.bin")?, &mut writer)?;
+/// writer.finish()?;
+/// image.save("encoded.png", ImageFormat::Png)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct StegoWriter<'a> {
+    image: &'a mut Image,
+    layout: Layout,
+    key: Option<StegoKey>,
+    matching: bool,
+    /// Where the next payload byte goes
+    cursor: Cursor,
+    written: usize,
+    capacity: usize,
+    hasher: crc32fast::Hasher,
+}
+
+impl<'a> StegoWriter<'a> {
+    pub fn new(image: &'a mut Image) -> Self {
+        Self::with_options(image, &EncodeOptions::default())
+            .expect("default options suit every image")
+    }
+
+    /// Stream a payload in with the given layout, stego key and LSB matching
+    ///
+    /// Options that need the whole payload up front (compression, error
+    /// correction, matrix, trellis and adaptive embedding) are rejected.
+    pub fn with_options(
+        image: &'a mut Image,
+        options: &EncodeOptions,
+    ) -> Result<Self, ImgSteganoError> {
+        let buffered = options.compression != Compression::None
+            || options.error_correction > 0
+            || options.matrix_embedding
+            || options.trellis.is_some()
+            || options.adaptive;
+        if buffered {
+            return Err(ImgSteganoError::InvalidOptions {
+                reason: "streamed payloads only support the layout, stego key and LSB matching",
+            });
+        }
+        lsb::normalize(&mut image.0);
+        options.layout.validate(&image.0)?;
+        let layout = options.layout.normalized(image.0.color());
+        let key = options.stego_key;
+
+        // Clear any previous header so an unfinished stream is not mistaken for one
+        lsb::write_bytes(
+            &mut image.0,
+            Layout::default(),
+            key.as_ref(),
+            false,
+            &[0; HEADER_SIZE],
+        );
+        let header = Header::new(&[]).with_layout(layout);
+        let prefix_size = header.prefix_size();
+        let slots = lsb::stream_slots(&image.0, layout, key.as_ref(), prefix_size);
+        let body_offset = (HEADER_SIZE + header.extension_size() - prefix_size) * 8;
+        Ok(StegoWriter {
+            capacity: image.capacity_with_options(options),
+            layout,
+            key,
+            matching: options.lsb_matching,
+            cursor: Cursor::new(slots, layout, body_offset),
+            image,
+            written: 0,
+            hasher: crc32fast::Hasher::new(),
+        })
+    }
+
+    /// Number of payload bytes written so far
+    pub fn written(&self) -> usize {
+        self.written
+    }
+
+    /// Number of payload bytes that can still be written
+    pub fn remaining(&self) -> usize {
+        self.capacity - self.written
+    }
+
+    /// Write the header, making the payload readable, and return its length
+    pub fn finish(self) -> Result<usize, ImgSteganoError> {
+        // Validate payload is not empty
+        if self.written == 0 {
+            return Err(ImgSteganoError::EmptyMessage);
+        }
+        let header = Header {
+            length: self.written as u32,
+            checksum: self.hasher.finalize(),
+            ..Header::new(&[])
+        }
+        .with_layout(self.layout);
+        lsb::write_stream(
+            &mut self.image.0,
+            self.layout,
+            self.key.as_ref(),
+            self.matching,
+            header.prefix_size(),
+            &header.to_bytes(),
+        );
+        Ok(self.written)
+    }
+}
+
+impl Write for StegoWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !buf.is_empty() && self.remaining() == 0 {
+            return Err(io::Error::other(ImgSteganoError::MessageTooLarge {
+                required: self.written + buf.len(),
+                available: self.capacity,
+            }));
+        }
+
+        let chunk = &buf[..buf.len().min(self.remaining())];
+        self.cursor
+            .write(&mut self.image.0, self.layout, self.matching, chunk);
+        self.hasher.update(chunk);
+        self.written += chunk.len();
+        Ok(chunk.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Streams a payload out of an image as it is read
+///
+/// Plain payloads are read lazily from the image LSBs and their checksum is
+/// verified once the end is reached; a mismatch is returned as an
+/// [`io::ErrorKind::InvalidData`] error wrapping
+/// [`ImgSteganoError::ChecksumMismatch`]. Compressed and error corrected
+/// payloads are decoded in memory first.
+pub struct StegoReader<'a> {
+    bytes: Box<dyn Iterator<Item = u8> + 'a>,
+    remaining: usize,
+    /// Running checksum and expected value, `None` once verified up front
+    verify: Option<(crc32fast::Hasher, u32)>,
+}
+
+impl<'a> StegoReader<'a> {
+    pub fn new(image: &'a Image) -> Result<Self, ImgSteganoError> {
+        Self::with_options(image, &DecodeOptions::default())
+    }
+
+    /// Stream out a payload embedded with a stego key
+    pub fn with_options(
+        image: &'a Image,
+        options: &DecodeOptions,
+    ) -> Result<Self, ImgSteganoError> {
+        let key = options.stego_key.as_ref();
+        let (layout, prefix_size, plain) =
+            read_prefix(&image.0, key).ok_or(ImgSteganoError::NoPayloadFound)?;
+        if !plain {
+            // Error corrected streams must be repaired as a whole
+            return Self::buffered(image, options);
+        }
+
+        let mut bytes = lsb::read_stream(&image.0, layout, key, prefix_size);
+        let header = Header::read(&mut bytes)?;
+        ensure_plaintext(&header)?;
+        if header.compressed.is_some() || header.coding.is_some() {
+            return Self::buffered(image, options);
+        }
+
+        // Reject lengths that could not have been written into this image
+        let length = header.length as usize;
+        let capacity = lsb::stream_capacity(&image.0, layout, key, prefix_size)
+            .saturating_sub(HEADER_SIZE + header.extension_size());
+        if length > capacity {
+            return Err(ImgSteganoError::InvalidLength {
+                length,
+                available: capacity,
+            });
+        }
+        Ok(StegoReader {
+            bytes: Box::new(bytes),
+            remaining: length,
+            verify: Some((crc32fast::Hasher::new(), header.checksum)),
+        })
+    }
+
+    fn buffered(encoded_image: &Image, options: &DecodeOptions) -> Result<Self, ImgSteganoError> {
+        let (header, body, _) = extract_with_options(encoded_image, options)?;
+        ensure_plaintext(&header)?;
+        let payload = decompress(&header, body)?;
+        Ok(StegoReader {
+            remaining: payload.len(),
+            bytes: Box::new(payload.into_iter()),
+            verify: None,
+        })
+    }
+
+    /// Number of payload bytes not yet read
+    pub fn remaining(&self) -> usize {
+        self.remaining
+    }
+}
+
+impl Read for StegoReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut read = 0;
+        for (slot, byte) in buf.iter_mut().zip(self.bytes.by_ref().take(self.remaining)) {
+            *slot = byte;
+            read += 1;
+        }
+        if read < buf.len().min(self.remaining) {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                ImgSteganoError::TruncatedPayload,
+            ));
+        }
+        self.remaining -= read;
+
+        if let Some((hasher, expected)) = &mut self.verify {
+            hasher.update(&buf[..read]);
+            if self.remaining == 0 {
+                let actual = hasher.clone().finalize();
+                let expected = *expected;
+                self.verify = None;
+                if actual != expected {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        ImgSteganoError::ChecksumMismatch { expected, actual },
+                    ));
+                }
+            }
+        }
+        Ok(read)
+    }
+}
@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use rand_chacha::ChaCha20Rng;
+use rand_core::{RngCore, SeedableRng};
+use sha2::{Digest, Sha256};
+
+/// Domain separation for deriving the traversal seed from a stego key
+const KEY_CONTEXT: &[u8] = b"img_stegano traversal v1";
+
+/// Secret that scatters the payload across the image
+///
+/// Derived from the caller's key bytes with SHA-256; the raw key is not kept.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) struct StegoKey([u8; 32]);
+
+impl StegoKey {
+    pub fn new(key: &[u8]) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(KEY_CONTEXT);
+        hasher.update(key);
+        StegoKey(hasher.finalize().into())
+    }
+}
+
+impl fmt::Debug for StegoKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("StegoKey(..)")
+    }
+}
+
+/// Order in which the `len` payload slots of an image are visited
+///
+/// Without a key slots are visited first to last; with one they follow a
+/// ChaCha20 seeded Fisher-Yates shuffle, computed lazily so reading a few
+/// leading bytes does not shuffle the whole image.
+pub(crate) fn order(len: usize, key: Option<&StegoKey>) -> Box<dyn Iterator<Item = usize>> {
+    match key {
+        None => Box::new(0..len),
+        Some(key) => Box::new(Permutation {
+            rng: ChaCha20Rng::from_seed(key.0),
+            len,
+            next: 0,
+            swapped: HashMap::new(),
+        }),
+    }
+}
+
+/// Fisher-Yates shuffle of `0..len` that only stores the displaced entries
+struct Permutation {
+    rng: ChaCha20Rng,
+    len: usize,
+    next: usize,
+    swapped: HashMap<usize, usize>,
+}
+
+impl Permutation {
+    /// Uniform value in `0..bound`, rejecting the biased top of the range
+    fn below(&mut self, bound: usize) -> usize {
+        let bound = bound as u64;
+        let zone = u64::MAX - u64::MAX % bound;
+        loop {
+            let value = self.rng.next_u64();
+            if value < zone {
+                return (value % bound) as usize;
+            }
+        }
+    }
+}
+
+impl Iterator for Permutation {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if self.next >= self.len {
+            return None;
+        }
+        let i = self.next;
+        let j = i + self.below(self.len - i);
+        self.next += 1;
+
+        let at_i = self.swapped.remove(&i).unwrap_or(i);
+        if j == i {
+            return Some(at_i);
+        }
+        Some(self.swapped.insert(j, at_i).unwrap_or(j))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.len - self.next;
+        (remaining, Some(remaining))
+    }
+}
@@ -0,0 +1,193 @@
+use rand_chacha::ChaCha20Rng;
+use rand_core::{RngCore, SeedableRng};
+
+use crate::matrix::{pack, unpack};
+use crate::ImgSteganoError;
+
+/// Coding scheme of syndrome-trellis codes, the parameter is the inverse rate `w`
+pub(crate) const SCHEME_TRELLIS: u8 = 2;
+
+/// Constraint height `h` of the code, giving a trellis of `2^h` states
+///
+/// Every state's predecessor choices are kept in one `u128` per cover bit.
+const HEIGHT: u32 = 7;
+const STATES: usize = 1 << HEIGHT;
+
+/// Message bits searched at a time, which bounds the path table kept by
+/// [`embed`] to `SEGMENT_BITS * w` entries whatever the payload size
+const SEGMENT_BITS: usize = 4096;
+
+/// Largest inverse rate, the number of cover bits spent on each message bit
+///
+/// Wider codes barely lower the cost any further, but the encoder keeps a
+/// path entry per cover bit, so the width bounds its memory and run time.
+const MAX_WIDTH: u8 = 20;
+
+/// Pick the inverse rate `w` for `length` bytes given `available` cover bytes
+///
+/// Each message bit is embedded into `w` cover bits, so the widest code
+/// that fits gives the encoder the most freedom in which bits to change.
+/// Returns `None` if the payload does not fit even with `w = 1`.
+pub(crate) fn choose(length: usize, available: usize) -> Option<u8> {
+    let width = available / length.max(1);
+    (width >= 1).then(|| width.min(MAX_WIDTH as usize) as u8)
+}
+
+/// Number of embedded bytes holding a `length` byte body coded with `width`
+///
+/// Returns `None` if the count overflows.
+pub(crate) fn coded_size(length: usize, width: u8) -> Option<usize> {
+    length.checked_mul(width as usize)
+}
+
+/// Columns of the `h x w` submatrix repeated along the diagonal of the
+/// parity check matrix
+///
+/// Bit `r` of a column is row `r` of the band. The first and last rows are
+/// always set, as good codes need, the rest is pseudo-random but fixed for
+/// each width so encoder and decoder agree on the code.
+fn submatrix(width: u8) -> Vec<usize> {
+    let mut rng = ChaCha20Rng::seed_from_u64(width as u64);
+    let mut middles: Vec<usize> = (0..STATES / 4).collect();
+    (0..width as usize)
+        .map(|i| {
+            // no column repeats before every possible one has been used
+            let i = i % middles.len();
+            if i == 0 {
+                for j in (1..middles.len()).rev() {
+                    middles.swap(j, rng.next_u32() as usize % (j + 1));
+                }
+            }
+            (middles[i] << 1) | 1 | (1 << (HEIGHT - 1))
+        })
+        .collect()
+}
+
+/// Band columns for message bit `block` of `message_bits`, cut off below the
+/// last message bit
+fn columns(
+    submatrix: &[usize],
+    block: usize,
+    message_bits: usize,
+) -> impl Iterator<Item = usize> + '_ {
+    let rows = (message_bits - block).min(HEIGHT as usize);
+    let mask = (1 << rows) - 1;
+    submatrix.iter().map(move |column| column & mask)
+}
+
+/// Recover a `length` byte body from its embedded bytes
+///
+/// The message is the syndrome of the stored bits: each message bit is the
+/// parity of the stored bits whose band columns cover its row.
+pub(crate) fn decode(stored: &[u8], length: usize, width: u8) -> Vec<u8> {
+    let submatrix = submatrix(width);
+    let message_bits = length * 8;
+    let mut stored_bits = unpack(stored);
+    let mut state = 0;
+    let bits = (0..message_bits).map(|block| {
+        for column in columns(&submatrix, block, message_bits) {
+            if stored_bits.next() == Some(1) {
+                state ^= column;
+            }
+        }
+        let bit = (state & 1) as u8;
+        state >>= 1;
+        bit
+    });
+    pack(bits)
+}
+
+/// Embed `message` into the `cover` bits at the least total cost
+///
+/// `costs` holds the cost of flipping each cover bit. The Viterbi algorithm
+/// runs over the trellis of partial syndromes, keeping for every state the
+/// cheapest way of reaching it, and the cheapest path whose syndrome equals
+/// the message is written back into `cover`. The search runs over
+/// [`SEGMENT_BITS`] message bits at a time, each segment continuing from
+/// the cheapest state the previous one ended in. Costs must be finite; the
+/// result is checked against the message all the same.
+pub(crate) fn embed(
+    cover: &mut [u8],
+    message: &[u8],
+    width: u8,
+    costs: &[f32],
+) -> Result<(), ImgSteganoError> {
+    let submatrix = submatrix(width);
+    let bits: Vec<u8> = unpack(message).collect();
+    let message_bits = bits.len();
+    let width = width as usize;
+
+    // sums of many large costs would overflow an f32
+    let mut weights = [f64::INFINITY; STATES];
+    weights[0] = 0.0;
+    // bit `s` of `paths[j]` is the stego bit `j` of the segment on the
+    // cheapest path to state `s`
+    let mut paths = vec![0u128; SEGMENT_BITS.min(message_bits) * width];
+    for start in (0..message_bits).step_by(SEGMENT_BITS) {
+        let segment = start..(start + SEGMENT_BITS).min(message_bits);
+        for block in segment.clone() {
+            for (k, column) in columns(&submatrix, block, message_bits).enumerate() {
+                let j = block * width + k;
+                let cost = costs[j] as f64;
+                let (keep, flip) = if bit_at(cover, j) == 0 {
+                    (0.0, cost)
+                } else {
+                    (cost, 0.0)
+                };
+                let previous = weights;
+                let mut path = 0u128;
+                for (state, weight) in weights.iter_mut().enumerate() {
+                    let zero = previous[state] + keep;
+                    let one = previous[state ^ column] + flip;
+                    if one < zero {
+                        *weight = one;
+                        path |= 1 << state;
+                    } else {
+                        *weight = zero;
+                    }
+                }
+                paths[(block - start) * width + k] = path;
+            }
+            // the row of this message bit leaves the band and must match it
+            let bit = bits[block] as usize;
+            let previous = weights;
+            for (state, weight) in weights.iter_mut().enumerate() {
+                *weight = if state < STATES / 2 {
+                    previous[(state << 1) | bit]
+                } else {
+                    f64::INFINITY
+                };
+            }
+        }
+
+        let end_state = (0..STATES)
+            .min_by(|&a, &b| weights[a].total_cmp(&weights[b]))
+            .unwrap_or_default();
+        let mut state = end_state;
+        for block in segment.rev() {
+            state = (state << 1) | bits[block] as usize;
+            let columns: Vec<usize> = columns(&submatrix, block, message_bits).collect();
+            for (k, column) in columns.into_iter().enumerate().rev() {
+                let j = block * width + k;
+                let stego_bit = (paths[(block - start) * width + k] >> state) & 1;
+                if stego_bit == 1 {
+                    state ^= column;
+                }
+                if stego_bit as u8 != bit_at(cover, j) {
+                    cover[j / 8] ^= 0x80 >> (j % 8);
+                }
+            }
+        }
+        weights = [f64::INFINITY; STATES];
+        weights[end_state] = 0.0;
+    }
+
+    if decode(cover, message.len(), width as u8) != message {
+        return Err(ImgSteganoError::EmbeddingFailed);
+    }
+    Ok(())
+}
+
+fn bit_at(bytes: &[u8], position: usize) -> u8 {
+    (bytes[position / 8] >> (7 - position % 8)) & 1
+}
@@ -0,0 +1,77 @@
+// Fixtures shared by several test files
+#![allow(dead_code)]
+
+use image::{DynamicImage, Rgb, RgbImage};
+use img_stegano::Image;
+
+/// Gradient in red and green over a constant blue
+pub fn create_test_image(width: u32, height: u32) -> Image {
+    DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| {
+        Rgb([(x % 256) as u8, (y % 256) as u8, 128])
+    }))
+    .into()
+}
+
+/// Payload of `len` bytes cycling through every byte value
+pub fn payload(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i * 7 % 256) as u8).collect()
+}
+
+/// Flip the LSB carrying the given bit of the embedded stream
+pub fn flip_lsb(image: Image, bit_index: usize) -> Image {
+    let mut img = DynamicImage::from(image).to_rgb8();
+    let width = img.width() as usize;
+    let pixel = bit_index / 3;
+    let (x, y) = ((pixel % width) as u32, (pixel / width) as u32);
+    img.get_pixel_mut(x, y).0[bit_index % 3] ^= 1;
+    DynamicImage::ImageRgb8(img).into()
+}
+
+/// Flat sky on the left half, noisy texture on the right half
+pub fn create_half_textured_image(width: u32, height: u32) -> DynamicImage {
+    DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| {
+        if x < width / 2 {
+            Rgb([120, 170, 230])
+        } else {
+            let noise = (x.wrapping_mul(2654435761) ^ y.wrapping_mul(40503)) >> 7;
+            Rgb([
+                (noise % 256) as u8,
+                (noise / 3 % 256) as u8,
+                (noise / 7 % 256) as u8,
+            ])
+        }
+    }))
+}
+
+/// Number of samples that differ between two images
+pub fn changed_samples(before: &DynamicImage, after: &DynamicImage) -> usize {
+    before
+        .as_bytes()
+        .iter()
+        .zip(after.as_bytes())
+        .filter(|(a, b)| a != b)
+        .count()
+}
+
+/// The first `size` bytes stored in the low bits of an RGB image's samples
+pub fn read_lsbs(image: &DynamicImage, size: usize) -> Vec<u8> {
+    image.to_rgb8().as_raw()[..size * 8]
+        .chunks(8)
+        .map(|bits| {
+            bits.iter()
+                .fold(0, |byte, sample| (byte << 1) | (sample & 1))
+        })
+        .collect()
+}
+
+/// Overwrite the low bits of an RGB image's samples with `bytes`
+pub fn write_lsbs(image: &DynamicImage, bytes: &[u8]) -> DynamicImage {
+    let mut rgb = image.to_rgb8();
+    let bits = bytes
+        .iter()
+        .flat_map(|byte| (0..8).rev().map(move |i| (byte >> i) & 1));
+    for (sample, bit) in rgb.iter_mut().zip(bits) {
+        *sample = (*sample & !1) | bit;
+    }
+    DynamicImage::ImageRgb8(rgb)
+}
@@ -0,0 +1,106 @@
+// Tests for embedding directly into 16-bit samples
+
+use image::{DynamicImage, GenericImageView, ImageBuffer, Luma, Rgb};
+use img_stegano::{
+    decode_bytes, decode_from_u8_array, encode_bytes, encode_bytes_with_options,
+    encode_from_u8_array, EncodeOptions, Image, ImgSteganoError,
+};
+
+fn create_rgb16_image(width: u32, height: u32) -> DynamicImage {
+    DynamicImage::ImageRgb16(ImageBuffer::from_fn(width, height, |x, y| {
+        Rgb([(x * 997) as u16, (y * 1009) as u16, 0xABCD])
+    }))
+}
+
+fn create_luma16_image(width: u32, height: u32) -> DynamicImage {
+    DynamicImage::ImageLuma16(ImageBuffer::from_fn(width, height, |x, y| {
+        Luma([((x + y) * 613) as u16])
+    }))
+}
+
+fn samples16(image: &DynamicImage) -> Vec<u16> {
+    match image {
+        DynamicImage::ImageRgb16(buffer) => buffer.as_raw().clone(),
+        DynamicImage::ImageLuma16(buffer) => buffer.as_raw().clone(),
+        _ => panic!("expected a 16-bit image, got {:?}", image.color()),
+    }
+}
+
+/// Assert two 16-bit images differ only in the lowest `bits` bits of each sample
+fn assert_only_low_bits_differ(before: &DynamicImage, after: &DynamicImage, bits: u8) {
+    assert_eq!(before.color(), after.color());
+    assert_eq!(before.dimensions(), after.dimensions());
+    for (a, b) in samples16(before).iter().zip(samples16(after)) {
+        assert_eq!(a >> bits, b >> bits);
+    }
+}
+
+#[test]
+fn test_rgb16_keeps_bit_depth() {
+    let original = create_rgb16_image(60, 60);
+    let encoded = encode_bytes(original.clone().into(), b"deep color").unwrap();
+    assert_eq!(decode_bytes(&encoded).unwrap(), b"deep color");
+
+    let encoded: DynamicImage = encoded.into();
+    assert!(matches!(encoded, DynamicImage::ImageRgb16(_)));
+    assert_only_low_bits_differ(&original, &encoded, 1);
+}
+
+#[test]
+fn test_luma16_keeps_bit_depth() {
+    let original = create_luma16_image(60, 60);
+    let encoded = encode_bytes(original.clone().into(), b"deep gray").unwrap();
+    assert_eq!(decode_bytes(&encoded).unwrap(), b"deep gray");
+
+    let encoded: DynamicImage = encoded.into();
+    assert!(matches!(encoded, DynamicImage::ImageLuma16(_)));
+    assert_only_low_bits_differ(&original, &encoded, 1);
+}
+
+#[test]
+fn test_16bit_accepts_up_to_8_bits_per_channel() {
+    let original = create_rgb16_image(40, 40);
+    let image: Image = original.clone().into();
+    let options = EncodeOptions::new().with_bits_per_channel(8);
+    // the fixed header and layout section take one bit of 128 samples
+    assert_eq!(image.capacity_with_options(&options), 40 * 40 * 3 - 128);
+
+    let payload: Vec<u8> = (0..4000).map(|i| (i * 7 % 256) as u8).collect();
+    let encoded = encode_bytes_with_options(image, &payload, &options).unwrap();
+    assert_eq!(decode_bytes(&encoded).unwrap(), payload);
+    assert_only_low_bits_differ(&original, &encoded.into(), 8);
+
+    let options = EncodeOptions::new().with_bits_per_channel(9);
+    let result = encode_bytes_with_options(original.into(), b"too many", &options);
+    assert!(matches!(
+        result,
+        Err(ImgSteganoError::InvalidOptions { .. })
+    ));
+}
+
+#[test]
+fn test_8bit_rejects_more_than_4_bits_per_channel() {
+    let image: Image = DynamicImage::new_rgb8(40, 40).into();
+    let options = EncodeOptions::new().with_bits_per_channel(5);
+    let result = encode_bytes_with_options(image, b"too many", &options);
+    assert!(matches!(
+        result,
+        Err(ImgSteganoError::InvalidOptions { .. })
+    ));
+}
+
+#[test]
+fn test_luma16_survives_png_round_trip() {
+    let original = create_luma16_image(60, 60);
+    let mut png = Vec::new();
+    original
+        .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+        .unwrap();
+
+    let encoded_png = encode_from_u8_array(&png, "sixteen bits").unwrap();
+    assert_eq!(decode_from_u8_array(&encoded_png).unwrap(), "sixteen bits");
+
+    let reloaded = image::load_from_memory(&encoded_png).unwrap();
+    assert!(matches!(reloaded, DynamicImage::ImageLuma16(_)));
+    assert_only_low_bits_differ(&original, &reloaded, 1);
+}
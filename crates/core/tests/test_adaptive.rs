@@ -0,0 +1,91 @@
+// Tests for content-adaptive embedding
+
+mod common;
+
+use common::create_half_textured_image;
+use image::{DynamicImage, GenericImageView};
+use img_stegano::{
+    decode_bytes, decode_bytes_with_options, encode_bytes_with_options, DecodeOptions,
+    EncodeOptions, Image, ImgSteganoError,
+};
+
+fn adaptive_options() -> EncodeOptions {
+    EncodeOptions::new().with_adaptive_embedding(true)
+}
+
+#[test]
+fn test_adaptive_round_trip() {
+    let payload: Vec<u8> = (0..500).map(|i| (i * 3 % 256) as u8).collect();
+    let encoded = encode_bytes_with_options(
+        create_half_textured_image(100, 100).into(),
+        &payload,
+        &adaptive_options(),
+    )
+    .unwrap();
+    assert_eq!(decode_bytes(&encoded).unwrap(), payload);
+}
+
+#[test]
+fn test_adaptive_avoids_flat_regions() {
+    let original = create_half_textured_image(100, 100);
+    let payload = vec![0xC3u8; 400];
+    let encoded: DynamicImage =
+        encode_bytes_with_options(original.clone().into(), &payload, &adaptive_options())
+            .unwrap()
+            .into();
+
+    // only the header sits in the first row, the body stays in the texture
+    for ((x, y, before), (_, _, after)) in original.pixels().zip(encoded.pixels()) {
+        if y > 0 && before != after {
+            assert!(x >= 49, "flat pixel ({x}, {y}) was changed");
+        }
+    }
+}
+
+#[test]
+fn test_adaptive_with_key_and_matrix_embedding() {
+    let options = adaptive_options()
+        .with_matrix_embedding(true)
+        .with_stego_key(b"texture")
+        .with_bits_per_channel(2);
+    let payload: Vec<u8> = (0..300).map(|i| (i % 256) as u8).collect();
+    let encoded = encode_bytes_with_options(
+        create_half_textured_image(100, 100).into(),
+        &payload,
+        &options,
+    )
+    .unwrap();
+
+    let options = DecodeOptions::new().with_stego_key(b"texture");
+    assert_eq!(
+        decode_bytes_with_options(&encoded, &options).unwrap(),
+        payload
+    );
+}
+
+#[test]
+fn test_adaptive_fills_capacity() {
+    let image: Image = create_half_textured_image(60, 60).into();
+    // three bytes of the capacity go to the coding section
+    let payload: Vec<u8> = (0..image.capacity() - 3).map(|i| (i % 256) as u8).collect();
+    let encoded = encode_bytes_with_options(image, &payload, &adaptive_options()).unwrap();
+    assert_eq!(decode_bytes(&encoded).unwrap(), payload);
+
+    let image: Image = create_half_textured_image(60, 60).into();
+    let payload = vec![0u8; image.capacity()];
+    assert!(matches!(
+        encode_bytes_with_options(image, &payload, &adaptive_options()),
+        Err(ImgSteganoError::MessageTooLarge { .. })
+    ));
+}
+
+#[test]
+fn test_adaptive_rejects_lsb_matching() {
+    let options = adaptive_options().with_lsb_matching(true);
+    let result =
+        encode_bytes_with_options(create_half_textured_image(40, 40).into(), b"x", &options);
+    assert!(matches!(
+        result,
+        Err(ImgSteganoError::InvalidOptions { .. })
+    ));
+}
@@ -0,0 +1,157 @@
+// Tests for hiding several files in one image
+
+mod common;
+
+use std::time::{Duration, UNIX_EPOCH};
+
+use common::{create_test_image, read_lsbs, write_lsbs};
+use image::DynamicImage;
+use img_stegano::{
+    encode_archive, encode_archive_with_options, encode_file, extract_archive,
+    extract_archive_with_options, extract_entry, extract_entry_with_options, list_archive,
+    list_archive_with_options, DecodeOptions, EncodeOptions, HiddenFile, Image, ImgSteganoError,
+};
+
+fn sample_files() -> Vec<HiddenFile> {
+    let mut notes = HiddenFile::new("notes.txt", b"meet at noon".to_vec());
+    notes.modified = Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+    vec![
+        notes,
+        HiddenFile::new("data.json", br#"{"id": 7}"#.to_vec()),
+        HiddenFile::new("empty.bin", Vec::new()),
+        HiddenFile::new("key.pem", (0..=255).collect()),
+    ]
+}
+
+#[test]
+fn test_archive_round_trip() {
+    let files = sample_files();
+    let encoded =
+        encode_archive(create_test_image(100, 100), &files).expect("Failed to encode archive");
+    let extracted = extract_archive(&encoded).expect("Failed to extract archive");
+    assert_eq!(extracted, files);
+}
+
+#[test]
+fn test_list_archive_entries() {
+    let files = sample_files();
+    let encoded =
+        encode_archive(create_test_image(100, 100), &files).expect("Failed to encode archive");
+    let entries = list_archive(&encoded).expect("Failed to list archive");
+
+    assert_eq!(entries.len(), files.len());
+    for (entry, file) in entries.iter().zip(&files) {
+        assert_eq!(entry.name, file.name);
+        assert_eq!(entry.mime, file.mime);
+        assert_eq!(entry.modified, file.modified);
+        assert_eq!(entry.size, file.data.len());
+    }
+}
+
+#[test]
+fn test_extract_single_entry() {
+    let files = sample_files();
+    let encoded =
+        encode_archive(create_test_image(100, 100), &files).expect("Failed to encode archive");
+
+    for file in &files {
+        let entry = extract_entry(&encoded, &file.name).expect("Failed to extract entry");
+        assert_eq!(&entry, file);
+    }
+
+    let missing = extract_entry(&encoded, "missing.txt");
+    assert!(
+        matches!(missing, Err(ImgSteganoError::EntryNotFound { name }) if name == "missing.txt")
+    );
+}
+
+#[test]
+fn test_duplicate_entry_names_rejected() {
+    let files = vec![
+        HiddenFile::new("a.txt", b"one".to_vec()),
+        HiddenFile::new("a.txt", b"two".to_vec()),
+    ];
+    let result = encode_archive(create_test_image(100, 100), &files);
+    assert!(matches!(
+        result,
+        Err(ImgSteganoError::InvalidFileMetadata { .. })
+    ));
+}
+
+#[test]
+fn test_empty_archive_rejected() {
+    let result = encode_archive(create_test_image(100, 100), &[]);
+    assert!(matches!(result, Err(ImgSteganoError::EmptyMessage)));
+}
+
+#[test]
+fn test_archive_and_file_payloads_are_distinct() {
+    let file = HiddenFile::new("single.txt", b"alone".to_vec());
+    let encoded = encode_file(create_test_image(100, 100), &file).unwrap();
+    assert!(matches!(
+        list_archive(&encoded),
+        Err(ImgSteganoError::NotAnArchive)
+    ));
+
+    let encoded = encode_archive(create_test_image(100, 100), &sample_files()).unwrap();
+    assert!(matches!(
+        img_stegano::extract_file(&encoded),
+        Err(ImgSteganoError::NotAFile)
+    ));
+}
+
+#[test]
+fn test_archive_with_stego_key() {
+    let files = sample_files();
+    let options = EncodeOptions::new().with_stego_key(b"archive key");
+    let encoded =
+        encode_archive_with_options(create_test_image(100, 100), &files, &options).unwrap();
+    assert!(list_archive(&encoded).is_err());
+
+    let options = DecodeOptions::new().with_stego_key(b"archive key");
+    let entries = list_archive_with_options(&encoded, &options).unwrap();
+    assert_eq!(entries.len(), files.len());
+    assert_eq!(
+        extract_archive_with_options(&encoded, &options).unwrap(),
+        files
+    );
+    assert_eq!(
+        extract_entry_with_options(&encoded, "data.json", &options).unwrap(),
+        files[1]
+    );
+}
+
+#[test]
+fn test_list_archive_reads_only_the_header() {
+    let files = sample_files();
+    let encoded = DynamicImage::from(encode_archive(create_test_image(100, 100), &files).unwrap());
+    // damage the body, well past the entry index
+    let mut stream = read_lsbs(&encoded, 300);
+    stream[290] ^= 0xFF;
+    let damaged: Image = write_lsbs(&encoded, &stream).into();
+
+    assert_eq!(list_archive(&damaged).unwrap().len(), files.len());
+    assert!(matches!(
+        extract_archive(&damaged),
+        Err(ImgSteganoError::ChecksumMismatch { .. })
+    ));
+
+    let options = EncodeOptions::new().with_error_correction(8);
+    let encoded =
+        encode_archive_with_options(create_test_image(100, 100), &files, &options).unwrap();
+    assert_eq!(list_archive(&encoded).unwrap().len(), files.len());
+}
+
+#[cfg(feature = "deflate")]
+#[test]
+fn test_extract_entry_from_compressed_archive() {
+    use img_stegano::Compression;
+
+    let files = sample_files();
+    let options = EncodeOptions::new().with_compression(Compression::Deflate);
+    let encoded =
+        encode_archive_with_options(create_test_image(100, 100), &files, &options).unwrap();
+    for file in &files {
+        assert_eq!(&extract_entry(&encoded, &file.name).unwrap(), file);
+    }
+}
@@ -0,0 +1,91 @@
+// Tests for embedding several bits per channel
+
+mod common;
+
+use common::{create_test_image, payload};
+use image::{DynamicImage, GenericImageView};
+use img_stegano::{
+    calculate_capacity, decode_bytes, decode_with_report, encode_bytes_with_options, EncodeOptions,
+    ImgSteganoError,
+};
+
+#[test]
+fn test_round_trip_for_each_bit_depth() {
+    for bits in 1..=4 {
+        let options = EncodeOptions::new().with_bits_per_channel(bits);
+        let image = create_test_image(50, 50);
+        let data = payload(image.capacity_with_options(&options));
+
+        let encoded =
+            encode_bytes_with_options(image, &data, &options).expect("Failed to encode payload");
+        assert_eq!(
+            decode_bytes(&encoded).expect("Failed to decode payload"),
+            data
+        );
+    }
+}
+
+#[test]
+fn test_capacity_scales_with_bit_depth() {
+    assert_eq!(calculate_capacity(100, 100, 1), 3750 - 14);
+    // with more bits the fixed header and layout section still take one
+    // bit of each of their 128 samples
+    assert_eq!(calculate_capacity(100, 100, 2), (30000 - 128) * 2 / 8);
+    assert_eq!(calculate_capacity(100, 100, 4), (30000 - 128) * 4 / 8);
+
+    let options = EncodeOptions::new().with_bits_per_channel(3);
+    assert_eq!(
+        create_test_image(100, 100).capacity_with_options(&options),
+        calculate_capacity(100, 100, 3)
+    );
+}
+
+#[test]
+fn test_changes_stay_within_low_bits() {
+    let original = create_test_image(40, 40);
+    let options = EncodeOptions::new().with_bits_per_channel(2);
+    let encoded = encode_bytes_with_options(original.clone(), &payload(500), &options).unwrap();
+
+    let (original, encoded): (DynamicImage, DynamicImage) = (original.into(), encoded.into());
+    for ((_, _, before), (_, _, after)) in original.pixels().zip(encoded.pixels()) {
+        for (a, b) in before.0.iter().zip(after.0) {
+            assert_eq!(a & !0b11, b & !0b11);
+        }
+    }
+}
+
+#[test]
+fn test_bit_depth_with_error_correction() {
+    let data = payload(1000);
+    let options = EncodeOptions::new()
+        .with_bits_per_channel(3)
+        .with_error_correction(16);
+    let encoded = encode_bytes_with_options(create_test_image(50, 50), &data, &options).unwrap();
+    let (decoded, report) = decode_with_report(&encoded).unwrap();
+    assert_eq!(decoded, data);
+    assert_eq!(report.corrected_errors, 0);
+}
+
+#[test]
+fn test_invalid_bit_depth_rejected() {
+    for bits in [0, 5, 8] {
+        let options = EncodeOptions::new().with_bits_per_channel(bits);
+        let result = encode_bytes_with_options(create_test_image(50, 50), b"data", &options);
+        assert!(matches!(
+            result,
+            Err(ImgSteganoError::InvalidOptions { .. })
+        ));
+    }
+}
+
+#[test]
+fn test_payload_too_large_for_bit_depth() {
+    let options = EncodeOptions::new().with_bits_per_channel(2);
+    let image = create_test_image(20, 20);
+    let too_large = payload(image.capacity_with_options(&options) + 1);
+    let result = encode_bytes_with_options(image, &too_large, &options);
+    assert!(matches!(
+        result,
+        Err(ImgSteganoError::MessageTooLarge { available, .. }) if available == calculate_capacity(20, 20, 2)
+    ));
+}
@@ -0,0 +1,100 @@
+// Tests for selecting which color channels carry the payload
+
+mod common;
+
+use common::create_test_image;
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+use img_stegano::{
+    decode_bytes, encode_bytes_with_options, Channels, EncodeOptions, Image, ImgSteganoError,
+};
+
+fn create_rgba_image(width: u32, height: u32) -> Image {
+    DynamicImage::ImageRgba8(RgbaImage::from_fn(width, height, |x, y| {
+        Rgba([(x % 256) as u8, (y % 256) as u8, 128, 200])
+    }))
+    .into()
+}
+
+/// Pixels holding the fixed header and layout section, which always go
+/// into the low bit of the first 128 color samples
+const HEADER_PIXELS: usize = 43;
+
+/// Indices of the RGBA channels that differ between two images, past the
+/// header pixels
+fn changed_channels(before: Image, after: Image) -> Vec<usize> {
+    let (before, after): (DynamicImage, DynamicImage) = (before.into(), after.into());
+    let mut changed: Vec<usize> = before
+        .pixels()
+        .zip(after.pixels())
+        .skip(HEADER_PIXELS)
+        .flat_map(|((_, _, a), (_, _, b))| (0..4).filter(move |&i| a.0[i] != b.0[i]))
+        .collect();
+    changed.sort_unstable();
+    changed.dedup();
+    changed
+}
+
+#[test]
+fn test_blue_only_round_trip() {
+    let image = create_test_image(50, 50);
+    let options = EncodeOptions::new().with_channels(Channels::BLUE);
+    let data = vec![0xA5; image.capacity_with_options(&options)];
+
+    let encoded = encode_bytes_with_options(image.clone(), &data, &options)
+        .expect("Failed to encode payload");
+    assert_eq!(decode_bytes(&encoded).expect("Failed to decode"), data);
+    assert_eq!(changed_channels(image, encoded), [2]);
+}
+
+#[test]
+fn test_alpha_only_leaves_color_untouched() {
+    let image = create_rgba_image(40, 40);
+    let options = EncodeOptions::new().with_channels(Channels::ALPHA);
+    let data = b"hidden in the alpha channel".to_vec();
+
+    let encoded = encode_bytes_with_options(image.clone(), &data, &options)
+        .expect("Failed to encode payload");
+    assert_eq!(decode_bytes(&encoded).expect("Failed to decode"), data);
+    assert_eq!(changed_channels(image, encoded), [3]);
+}
+
+#[test]
+fn test_all_four_channels_with_bit_depth() {
+    let image = create_rgba_image(30, 30);
+    let options = EncodeOptions::new()
+        .with_channels(Channels::RGBA)
+        .with_bits_per_channel(2);
+    assert_eq!(
+        image.capacity_with_options(&options),
+        (30 * 30 * 4 - 128) * 2 / 8
+    );
+
+    let data: Vec<u8> = (0..800).map(|i| i as u8).collect();
+    let encoded = encode_bytes_with_options(image, &data, &options).unwrap();
+    assert_eq!(decode_bytes(&encoded).unwrap(), data);
+}
+
+#[test]
+fn test_capacity_counts_selected_channels() {
+    let image = create_test_image(80, 80);
+    let red_green = EncodeOptions::new().with_channels(Channels::RED | Channels::GREEN);
+    // 86 of the 128 header samples are red or green
+    assert_eq!(
+        image.capacity_with_options(&red_green),
+        (80 * 80 * 2 - 86) / 8
+    );
+
+    // an RGB image has no alpha to count
+    let all = EncodeOptions::new().with_channels(Channels::RGBA);
+    assert_eq!(image.capacity_with_options(&all), image.capacity());
+}
+
+#[test]
+fn test_alpha_requires_alpha_channel() {
+    let options = EncodeOptions::new().with_channels(Channels::ALPHA);
+    let result = encode_bytes_with_options(create_test_image(40, 40), b"data", &options);
+    assert!(matches!(
+        result,
+        Err(ImgSteganoError::InvalidOptions { .. })
+    ));
+}
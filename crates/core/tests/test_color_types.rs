@@ -0,0 +1,116 @@
+// Tests that encoding keeps the cover's color type, alpha and non-payload bits
+
+use image::{
+    DynamicImage, GenericImageView, GrayAlphaImage, GrayImage, Luma, LumaA, Rgba, RgbaImage,
+};
+use img_stegano::{
+    decode_bytes, decode_from_u8_array, encode_bytes, encode_bytes_with_options,
+    encode_from_u8_array, Channels, EncodeOptions, Image,
+};
+
+fn create_rgba_image(width: u32, height: u32) -> DynamicImage {
+    // fully transparent border around a semi-transparent center
+    DynamicImage::ImageRgba8(RgbaImage::from_fn(width, height, |x, y| {
+        let alpha = if x < 5 || y < 5 { 0 } else { 77 };
+        Rgba([(x % 256) as u8, (y % 256) as u8, 128, alpha])
+    }))
+}
+
+fn create_luma_alpha_image(width: u32, height: u32) -> DynamicImage {
+    DynamicImage::ImageLumaA8(GrayAlphaImage::from_fn(width, height, |x, y| {
+        LumaA([((x + y) % 256) as u8, if x % 2 == 0 { 0 } else { 200 }])
+    }))
+}
+
+/// Assert two images differ only in the lowest bit of each sample
+fn assert_only_lsbs_differ(before: &DynamicImage, after: &DynamicImage) {
+    assert_eq!(before.color(), after.color());
+    assert_eq!(before.dimensions(), after.dimensions());
+    for (a, b) in before.as_bytes().iter().zip(after.as_bytes()) {
+        assert_eq!(a >> 1, b >> 1);
+    }
+}
+
+#[test]
+fn test_rgba_keeps_transparency() {
+    let original = create_rgba_image(60, 60);
+    let encoded = encode_bytes(original.clone().into(), b"transparent icon").unwrap();
+    assert_eq!(decode_bytes(&encoded).unwrap(), b"transparent icon");
+
+    let encoded: DynamicImage = encoded.into();
+    assert!(matches!(encoded, DynamicImage::ImageRgba8(_)));
+    assert_only_lsbs_differ(&original, &encoded);
+    for ((_, _, before), (_, _, after)) in original.pixels().zip(encoded.pixels()) {
+        assert_eq!(before.0[3], after.0[3]);
+    }
+}
+
+#[test]
+fn test_rgba_survives_png_round_trip() {
+    let original = create_rgba_image(60, 60);
+    let mut png = Vec::new();
+    original
+        .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+        .unwrap();
+
+    let encoded_png = encode_from_u8_array(&png, "still transparent").unwrap();
+    assert_eq!(
+        decode_from_u8_array(&encoded_png).unwrap(),
+        "still transparent"
+    );
+    let reloaded = image::load_from_memory(&encoded_png).unwrap();
+    assert!(matches!(reloaded, DynamicImage::ImageRgba8(_)));
+    assert_only_lsbs_differ(&original, &reloaded);
+}
+
+#[test]
+fn test_luma_alpha_stays_grayscale() {
+    let original = create_luma_alpha_image(60, 60);
+    let data: Vec<u8> = (0..100).collect();
+    let encoded = encode_bytes(original.clone().into(), &data).unwrap();
+    assert_eq!(decode_bytes(&encoded).unwrap(), data);
+
+    let encoded: DynamicImage = encoded.into();
+    assert!(matches!(encoded, DynamicImage::ImageLumaA8(_)));
+    assert_only_lsbs_differ(&original, &encoded);
+    // only the gray samples carry data by default
+    for (before, after) in original
+        .as_bytes()
+        .chunks(2)
+        .zip(encoded.as_bytes().chunks(2))
+    {
+        assert_eq!(before[1], after[1]);
+    }
+}
+
+#[test]
+fn test_luma_alpha_with_alpha_channel() {
+    let original = create_luma_alpha_image(40, 40);
+    let options = EncodeOptions::new().with_channels(Channels::ALPHA);
+    let encoded = encode_bytes_with_options(original.clone().into(), b"alpha", &options).unwrap();
+    assert_eq!(decode_bytes(&encoded).unwrap(), b"alpha");
+
+    // past the fixed header, which takes the low bit of 128 gray samples
+    let encoded: DynamicImage = encoded.into();
+    for (before, after) in original
+        .as_bytes()
+        .chunks(2)
+        .zip(encoded.as_bytes().chunks(2))
+        .skip(128)
+    {
+        assert_eq!(before[0], after[0]);
+    }
+}
+
+#[test]
+fn test_luma_stays_grayscale() {
+    let original = DynamicImage::ImageLuma8(GrayImage::from_fn(40, 40, |x, y| {
+        Luma([((x * y) % 256) as u8])
+    }));
+    let encoded = encode_bytes(Image::from(original.clone()), b"gray").unwrap();
+    assert_eq!(decode_bytes(&encoded).unwrap(), b"gray");
+
+    let encoded: DynamicImage = encoded.into();
+    assert!(matches!(encoded, DynamicImage::ImageLuma8(_)));
+    assert_only_lsbs_differ(&original, &encoded);
+}
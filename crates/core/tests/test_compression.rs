@@ -0,0 +1,85 @@
+// Tests for transparent payload compression
+#![cfg(all(feature = "deflate", feature = "zstd"))]
+
+mod common;
+
+use common::create_test_image;
+use img_stegano::{
+    decode_bytes, decode_from_image, encode_bytes_with_options, Compression, EncodeOptions,
+    ImgSteganoError,
+};
+
+/// Repetitive log-like text that compresses well
+fn log_payload(lines: usize) -> String {
+    (0..lines)
+        .map(|i| {
+            format!(
+                "2024-01-01T00:00:{:02}Z INFO request handled status=200\n",
+                i % 60
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn test_compressed_round_trip() {
+    for compression in [Compression::Deflate, Compression::Zstd] {
+        let payload = log_payload(50);
+        let options = EncodeOptions::new().with_compression(compression);
+        let encoded =
+            encode_bytes_with_options(create_test_image(100, 100), payload.as_bytes(), &options)
+                .expect("Failed to encode compressed payload");
+        let decoded = decode_from_image(&encoded).expect("Failed to decode compressed payload");
+        assert_eq!(decoded, payload);
+    }
+}
+
+#[test]
+fn test_compression_exceeds_raw_capacity() {
+    let image = create_test_image(60, 60);
+    let payload = log_payload(100);
+    // Too large to store raw, but fits once compressed
+    assert!(payload.len() > image.capacity());
+
+    let plain = encode_bytes_with_options(image.clone(), payload.as_bytes(), &EncodeOptions::new());
+    assert!(matches!(
+        plain,
+        Err(ImgSteganoError::MessageTooLarge { .. })
+    ));
+
+    let options = EncodeOptions::new().with_compression(Compression::Zstd);
+    let encoded = encode_bytes_with_options(image, payload.as_bytes(), &options)
+        .expect("Failed to encode compressed payload");
+    assert_eq!(
+        decode_bytes(&encoded).expect("Failed to decode"),
+        payload.as_bytes()
+    );
+}
+
+#[test]
+fn test_incompressible_payload_stored_raw() {
+    let image = create_test_image(100, 100);
+    let payload: Vec<u8> = (0..200u32)
+        .map(|i| (i.wrapping_mul(2654435761) >> 13) as u8)
+        .collect();
+    let options = EncodeOptions::new().with_compression(Compression::Deflate);
+    let encoded = encode_bytes_with_options(image, &payload, &options).expect("Failed to encode");
+    assert_eq!(decode_bytes(&encoded).expect("Failed to decode"), payload);
+}
+
+#[test]
+fn test_estimated_capacity() {
+    let image = create_test_image(100, 100);
+    let sample = log_payload(20);
+
+    assert_eq!(
+        image
+            .estimated_capacity(sample.as_bytes(), Compression::None)
+            .unwrap(),
+        image.capacity()
+    );
+    let estimated = image
+        .estimated_capacity(sample.as_bytes(), Compression::Deflate)
+        .unwrap();
+    assert!(estimated > image.capacity() * 2);
+}
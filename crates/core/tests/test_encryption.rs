@@ -0,0 +1,62 @@
+// Tests for password-based payload encryption
+#![cfg(feature = "encrypt")]
+
+mod common;
+
+use common::create_test_image;
+use img_stegano::{
+    decode_bytes, decode_encrypted, encode_bytes, encode_encrypted, ImgSteganoError,
+};
+
+const PASSWORD: &str = "correct horse battery staple";
+
+#[test]
+fn test_encrypted_round_trip() {
+    let payload = b"attack at dawn\0with binary tail";
+    let encoded = encode_encrypted(create_test_image(100, 100), payload, PASSWORD)
+        .expect("Failed to encode encrypted payload");
+    let decoded = decode_encrypted(&encoded, PASSWORD).expect("Failed to decode payload");
+    assert_eq!(decoded, payload);
+}
+
+#[test]
+fn test_wrong_password() {
+    let encoded = encode_encrypted(create_test_image(100, 100), b"secret", PASSWORD)
+        .expect("Failed to encode encrypted payload");
+    let result = decode_encrypted(&encoded, "hunter2");
+    assert!(matches!(
+        result,
+        Err(ImgSteganoError::WrongPasswordOrCorrupt)
+    ));
+}
+
+#[test]
+fn test_plain_decode_requires_password() {
+    let encoded = encode_encrypted(create_test_image(100, 100), b"secret", PASSWORD)
+        .expect("Failed to encode encrypted payload");
+    let result = decode_bytes(&encoded);
+    assert!(matches!(result, Err(ImgSteganoError::PasswordRequired)));
+}
+
+#[test]
+fn test_decrypting_plain_payload_fails() {
+    let encoded =
+        encode_bytes(create_test_image(100, 100), b"plain").expect("Failed to encode payload");
+    let result = decode_encrypted(&encoded, PASSWORD);
+    assert!(matches!(
+        result,
+        Err(ImgSteganoError::WrongPasswordOrCorrupt)
+    ));
+}
+
+#[test]
+fn test_encryption_overhead_counts_against_capacity() {
+    let image = create_test_image(20, 20);
+    // Fits unencrypted, but not once salt, nonce and tag are added
+    let payload = vec![b'A'; image.capacity()];
+    let result = encode_encrypted(image, &payload, PASSWORD);
+    assert!(matches!(
+        result,
+        Err(ImgSteganoError::MessageTooLarge { .. })
+    ));
+}
@@ -0,0 +1,195 @@
+// Tests for Reed-Solomon forward error correction
+
+mod common;
+
+use common::{create_test_image, flip_lsb, write_lsbs};
+use img_stegano::{
+    decode_bytes, decode_from_image, decode_with_report, encode_bytes_with_options,
+    encode_from_image, EncodeOptions, Image, ImgSteganoError,
+};
+
+const SECRET_MESSAGE: &str = "The quick brown fox jumps over the lazy dog";
+
+/// Flip the LSBs carrying the given bits of the embedded stream
+fn flip_lsbs(image: Image, bit_indices: &[usize]) -> Image {
+    bit_indices
+        .iter()
+        .fold(image, |image, &bit| flip_lsb(image, bit))
+}
+
+fn encode_with_parity(image: Image, payload: &[u8], parity: u8) -> Image {
+    let options = EncodeOptions::new().with_error_correction(parity);
+    encode_bytes_with_options(image, payload, &options).expect("Failed to encode")
+}
+
+#[test]
+fn test_error_correction_round_trip_without_errors() {
+    let encoded = encode_with_parity(create_test_image(100, 100), SECRET_MESSAGE.as_bytes(), 16);
+    let (decoded, report) = decode_with_report(&encoded).expect("Failed to decode");
+    assert_eq!(decoded, SECRET_MESSAGE.as_bytes());
+    assert_eq!(report.corrected_errors, 0);
+}
+
+#[test]
+fn test_corrects_scattered_bit_errors() {
+    let encoded = encode_with_parity(create_test_image(100, 100), SECRET_MESSAGE.as_bytes(), 16);
+
+    // One flipped bit in each of 6 different bytes after the 17 byte preamble
+    let errors: Vec<usize> = (0..6).map(|i| (20 + i * 7) * 8 + i).collect();
+    let damaged = flip_lsbs(encoded, &errors);
+
+    let (decoded, report) = decode_with_report(&damaged).expect("Failed to decode");
+    assert_eq!(decoded, SECRET_MESSAGE.as_bytes());
+    assert_eq!(report.corrected_errors, 6);
+    assert_eq!(decode_from_image(&damaged).unwrap(), SECRET_MESSAGE);
+}
+
+#[test]
+fn test_corrects_damaged_preamble() {
+    let encoded = encode_with_parity(create_test_image(100, 100), SECRET_MESSAGE.as_bytes(), 8);
+
+    // Damage the magic and parity bytes of the preamble
+    let damaged = flip_lsbs(encoded, &[0, 13, 4 * 8 + 2]);
+
+    let (decoded, report) = decode_with_report(&damaged).expect("Failed to decode");
+    assert_eq!(decoded, SECRET_MESSAGE.as_bytes());
+    assert_eq!(report.corrected_errors, 3);
+}
+
+#[test]
+fn test_corrects_errors_across_multiple_blocks() {
+    let payload: Vec<u8> = (0..1000u32).map(|i| (i * 7 % 251) as u8).collect();
+    let encoded = encode_with_parity(create_test_image(200, 200), &payload, 32);
+
+    // A burst of 10 consecutive bytes in the second and third blocks
+    let errors: Vec<usize> = (0..10)
+        .flat_map(|i| [(300 + i) * 8 + 1, (560 + i) * 8 + 6])
+        .collect();
+    let damaged = flip_lsbs(encoded, &errors);
+
+    let (decoded, report) = decode_with_report(&damaged).expect("Failed to decode");
+    assert_eq!(decoded, payload);
+    assert_eq!(report.corrected_errors, 20);
+}
+
+#[test]
+fn test_too_many_errors_reported() {
+    let encoded = encode_with_parity(create_test_image(100, 100), SECRET_MESSAGE.as_bytes(), 4);
+
+    // Three corrupted bytes exceed what 4 parity bytes can repair
+    let damaged = flip_lsbs(encoded, &[20 * 8, 25 * 8, 30 * 8]);
+    let result = decode_bytes(&damaged);
+    assert!(matches!(
+        result,
+        Err(ImgSteganoError::UncorrectableErrors) | Err(ImgSteganoError::ChecksumMismatch { .. })
+    ));
+}
+
+#[test]
+fn test_same_errors_without_correction_fail() {
+    let encoded =
+        encode_from_image(create_test_image(100, 100), SECRET_MESSAGE).expect("Failed to encode");
+    let damaged = flip_lsbs(encoded, &[20 * 8]);
+    let result = decode_bytes(&damaged);
+    assert!(matches!(
+        result,
+        Err(ImgSteganoError::ChecksumMismatch { .. })
+    ));
+}
+
+#[test]
+fn test_plain_payload_reports_no_corrections() {
+    let encoded =
+        encode_from_image(create_test_image(100, 100), SECRET_MESSAGE).expect("Failed to encode");
+    let (_, report) = decode_with_report(&encoded).expect("Failed to decode");
+    assert_eq!(report.corrected_errors, 0);
+}
+
+#[test]
+fn test_parity_counts_against_capacity() {
+    let image = create_test_image(30, 30);
+    let payload = vec![b'A'; image.capacity()];
+    let options = EncodeOptions::new().with_error_correction(32);
+    let result = encode_bytes_with_options(image, &payload, &options);
+    assert!(matches!(
+        result,
+        Err(ImgSteganoError::MessageTooLarge { .. })
+    ));
+}
+
+#[test]
+fn test_invalid_parity_rejected() {
+    let options = EncodeOptions::new().with_error_correction(255);
+    let result = encode_bytes_with_options(create_test_image(100, 100), b"x", &options);
+    assert!(matches!(
+        result,
+        Err(ImgSteganoError::InvalidOptions { .. })
+    ));
+}
+
+/// Product in GF(256) with the primitive polynomial 0x11d
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0;
+    while b != 0 {
+        if b & 1 != 0 {
+            product ^= a;
+        }
+        let carry = a & 0x80 != 0;
+        a <<= 1;
+        if carry {
+            a ^= 0x1d;
+        }
+        b >>= 1;
+    }
+    product
+}
+
+/// `data` followed by `parity` Reed-Solomon parity bytes
+fn rs_block(data: &[u8], parity: usize) -> Vec<u8> {
+    let mut generator = vec![1u8];
+    let mut root = 1u8;
+    for _ in 0..parity {
+        let mut next = generator.clone();
+        next.push(0);
+        for (i, &coefficient) in generator.iter().enumerate() {
+            next[i + 1] ^= gf_mul(coefficient, root);
+        }
+        generator = next;
+        root = gf_mul(root, 2);
+    }
+    let mut remainder = vec![0u8; parity];
+    for &byte in data {
+        let factor = byte ^ remainder.remove(0);
+        remainder.push(0);
+        for (r, &g) in remainder.iter_mut().zip(&generator[1..]) {
+            *r ^= gf_mul(g, factor);
+        }
+    }
+    let mut block = data.to_vec();
+    block.extend(remainder);
+    block
+}
+
+#[test]
+fn test_rejects_cost_map_in_corrected_stream() {
+    // a 17 byte header with an adaptive coding section, shorter than the
+    // 23 byte preamble in front of it
+    let body = b"evil";
+    let mut stream = b"ISTG\x01\x40".to_vec();
+    stream.extend_from_slice(&(body.len() as u32).to_be_bytes());
+    stream.extend_from_slice(&crc32fast::hash(body).to_be_bytes());
+    stream.extend_from_slice(&[1, 1, 1]);
+    stream.extend_from_slice(body);
+
+    let mut preamble = b"ISTF\x04".to_vec();
+    preamble.extend_from_slice(&(stream.len() as u32).to_be_bytes());
+    preamble.extend_from_slice(&[1, 0b0111]);
+    let mut encoded = rs_block(&preamble, 8);
+    encoded.extend(rs_block(&stream, 4));
+
+    let image = write_lsbs(&create_test_image(40, 40).into(), &encoded);
+    assert!(matches!(
+        decode_bytes(&image.into()),
+        Err(ImgSteganoError::UnsupportedCostMap { cost_map: 1 })
+    ));
+}
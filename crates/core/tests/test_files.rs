@@ -0,0 +1,130 @@
+// Tests for hiding whole files with their metadata
+
+mod common;
+
+use common::create_test_image;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use img_stegano::{
+    decode_bytes, encode_bytes, encode_file, encode_file_with_options, extract_file,
+    extract_file_with_options, mime_from_name, DecodeOptions, EncodeOptions, HiddenFile,
+    ImgSteganoError, DEFAULT_MIME,
+};
+
+fn temp_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("img_stegano_{name}_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("Failed to create temp dir");
+    dir
+}
+
+#[test]
+fn test_file_round_trip_keeps_metadata() {
+    let mut file = HiddenFile::new("config.toml", b"[server]\nport = 8080\n".to_vec());
+    file.modified = Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+
+    let encoded = encode_file(create_test_image(100, 100), &file).expect("Failed to encode file");
+    let extracted = extract_file(&encoded).expect("Failed to extract file");
+
+    assert_eq!(extracted, file);
+    assert_eq!(extracted.mime, "application/toml");
+}
+
+#[test]
+fn test_binary_file_without_modified_time() {
+    let file = HiddenFile {
+        name: "id_ed25519".to_string(),
+        mime: DEFAULT_MIME.to_string(),
+        modified: None,
+        data: (0..=255).collect(),
+    };
+    let encoded = encode_file(create_test_image(100, 100), &file).expect("Failed to encode file");
+    assert_eq!(
+        extract_file(&encoded).expect("Failed to extract file"),
+        file
+    );
+}
+
+#[test]
+fn test_file_data_readable_as_bytes() {
+    let file = HiddenFile::new("notes.txt", b"remember the milk".to_vec());
+    let encoded = encode_file(create_test_image(100, 100), &file).expect("Failed to encode file");
+    assert_eq!(decode_bytes(&encoded).unwrap(), b"remember the milk");
+}
+
+#[test]
+fn test_extract_file_from_plain_payload() {
+    let encoded = encode_bytes(create_test_image(100, 100), b"just bytes").unwrap();
+    let result = extract_file(&encoded);
+    assert!(matches!(result, Err(ImgSteganoError::NotAFile)));
+}
+
+#[test]
+fn test_file_from_path_and_write_to_dir() {
+    let source_dir = temp_dir("source");
+    let target_dir = temp_dir("target");
+    let source = source_dir.join("report.pdf");
+    std::fs::write(&source, b"%PDF-1.4 fake").unwrap();
+
+    let file = HiddenFile::from_path(&source).expect("Failed to read file");
+    assert_eq!(file.name, "report.pdf");
+    assert_eq!(file.mime, "application/pdf");
+    assert!(file.modified.is_some());
+
+    let encoded = encode_file(create_test_image(100, 100), &file).expect("Failed to encode file");
+    let extracted = extract_file(&encoded).expect("Failed to extract file");
+    let written = extracted
+        .write_to_dir(&target_dir)
+        .expect("Failed to write file");
+
+    assert_eq!(written, target_dir.join("report.pdf"));
+    assert_eq!(std::fs::read(&written).unwrap(), b"%PDF-1.4 fake");
+    let modified = std::fs::metadata(&written).unwrap().modified().unwrap();
+    let secs = |time: SystemTime| time.duration_since(UNIX_EPOCH).unwrap().as_secs();
+    assert_eq!(secs(modified), secs(file.modified.unwrap()));
+}
+
+#[test]
+fn test_write_to_dir_ignores_directory_components() {
+    let target_dir = temp_dir("traversal");
+    let file = HiddenFile::new("../../escape.txt", b"nope".to_vec());
+
+    let written = file
+        .write_to_dir(&target_dir)
+        .expect("Failed to write file");
+    assert_eq!(written, target_dir.join("escape.txt"));
+}
+
+#[test]
+fn test_invalid_file_names_rejected() {
+    let empty = HiddenFile::new("", b"data".to_vec());
+    let result = encode_file(create_test_image(100, 100), &empty);
+    assert!(matches!(
+        result,
+        Err(ImgSteganoError::InvalidFileMetadata { .. })
+    ));
+
+    let long = HiddenFile::new("a".repeat(300), b"data".to_vec());
+    let result = encode_file(create_test_image(100, 100), &long);
+    assert!(matches!(
+        result,
+        Err(ImgSteganoError::InvalidFileMetadata { .. })
+    ));
+}
+
+#[test]
+fn test_mime_from_name() {
+    assert_eq!(mime_from_name("photo.JPG"), "image/jpeg");
+    assert_eq!(mime_from_name("data.json"), "application/json");
+    assert_eq!(mime_from_name("no_extension"), DEFAULT_MIME);
+}
+
+#[test]
+fn test_file_with_stego_key() {
+    let file = HiddenFile::new("notes.md", b"# scattered\n".to_vec());
+    let options = EncodeOptions::new().with_stego_key(b"file key");
+    let encoded = encode_file_with_options(create_test_image(100, 100), &file, &options).unwrap();
+
+    let options = DecodeOptions::new().with_stego_key(b"file key");
+    assert_eq!(extract_file_with_options(&encoded, &options).unwrap(), file);
+    assert!(extract_file(&encoded).is_err());
+}
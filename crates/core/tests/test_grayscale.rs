@@ -0,0 +1,74 @@
+// Tests for embedding into grayscale covers without converting them to color
+
+use image::{DynamicImage, GrayAlphaImage, GrayImage, Luma};
+use img_stegano::{
+    decode_bytes, encode_bytes, encode_bytes_with_options, encode_from_u8_array, Channels,
+    EncodeOptions, Image, ImgSteganoError,
+};
+
+fn create_gray_image(width: u32, height: u32) -> DynamicImage {
+    DynamicImage::ImageLuma8(GrayImage::from_fn(width, height, |x, y| {
+        Luma([((x * 3 + y * 5) % 256) as u8])
+    }))
+}
+
+fn png_bytes(image: &DynamicImage) -> Vec<u8> {
+    let mut png = Vec::new();
+    image
+        .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+        .unwrap();
+    png
+}
+
+#[test]
+fn test_gray_capacity_is_one_sample_per_pixel() {
+    let gray: Image = create_gray_image(100, 100).into();
+    assert_eq!(gray.capacity(), 100 * 100 / 8 - 14);
+
+    let gray_alpha: Image = DynamicImage::ImageLumaA8(GrayAlphaImage::new(100, 100)).into();
+    assert_eq!(gray_alpha.capacity(), 100 * 100 / 8 - 14);
+    // the fixed header and layout section take one bit of 128 gray samples
+    let options = EncodeOptions::new().with_channels(Channels::RGBA);
+    assert_eq!(
+        gray_alpha.capacity_with_options(&options),
+        (2 * 100 * 100 - 128) / 8
+    );
+}
+
+#[test]
+fn test_gray_fills_capacity() {
+    let image: Image = create_gray_image(80, 80).into();
+    let payload: Vec<u8> = (0..image.capacity()).map(|i| (i % 251) as u8).collect();
+    let encoded = encode_bytes(image, &payload).unwrap();
+    assert_eq!(decode_bytes(&encoded).unwrap(), payload);
+
+    let image: Image = create_gray_image(80, 80).into();
+    let too_large = vec![0u8; image.capacity() + 1];
+    assert!(matches!(
+        encode_bytes(image, &too_large),
+        Err(ImgSteganoError::MessageTooLarge { .. })
+    ));
+}
+
+#[test]
+fn test_any_color_channel_selects_gray_sample() {
+    let options = EncodeOptions::new().with_channels(Channels::BLUE);
+    let image: Image = create_gray_image(40, 40).into();
+    assert_eq!(image.capacity_with_options(&options), image.capacity());
+
+    let encoded = encode_bytes_with_options(image, b"blue is gray", &options).unwrap();
+    assert_eq!(decode_bytes(&encoded).unwrap(), b"blue is gray");
+}
+
+#[test]
+fn test_gray_png_stays_single_channel() {
+    let original = create_gray_image(64, 64);
+    let png = png_bytes(&original);
+    let encoded_png = encode_from_u8_array(&png, "scanned page").unwrap();
+
+    let reloaded = image::load_from_memory(&encoded_png).unwrap();
+    assert!(matches!(reloaded, DynamicImage::ImageLuma8(_)));
+    // an RGBA copy of the same pixels would be several times larger
+    let as_rgba = png_bytes(&DynamicImage::ImageRgba8(original.to_rgba8()));
+    assert!(encoded_png.len() < as_rgba.len());
+}
@@ -0,0 +1,87 @@
+// Tests for LSB matching (±1 embedding)
+
+use image::{DynamicImage, Rgb, RgbImage};
+use img_stegano::{decode_bytes, encode_bytes_with_options, EncodeOptions};
+
+fn create_test_image(width: u32, height: u32) -> DynamicImage {
+    DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| {
+        Rgb([
+            (x * 5 % 256) as u8,
+            (y * 9 % 256) as u8,
+            ((x + y) % 256) as u8,
+        ])
+    }))
+}
+
+fn create_flat_image(width: u32, height: u32, value: u8) -> DynamicImage {
+    DynamicImage::ImageRgb8(RgbImage::from_pixel(width, height, Rgb([value; 3])))
+}
+
+fn encode_matching(image: &DynamicImage, payload: &[u8], bits: u8) -> DynamicImage {
+    let options = EncodeOptions::new()
+        .with_lsb_matching(true)
+        .with_bits_per_channel(bits);
+    let encoded = encode_bytes_with_options(image.clone().into(), payload, &options).unwrap();
+    assert_eq!(decode_bytes(&encoded).unwrap(), payload);
+    encoded.into()
+}
+
+/// Signed change of every sample
+fn differences(before: &DynamicImage, after: &DynamicImage) -> Vec<i16> {
+    before
+        .as_bytes()
+        .iter()
+        .zip(after.as_bytes())
+        .map(|(&a, &b)| b as i16 - a as i16)
+        .collect()
+}
+
+#[test]
+fn test_matching_changes_samples_by_one() {
+    let original = create_test_image(80, 80);
+    let payload: Vec<u8> = (0..500).map(|i| (i * 31 % 256) as u8).collect();
+    let encoded = encode_matching(&original, &payload, 1);
+
+    let differences = differences(&original, &encoded);
+    assert!(differences.iter().all(|d| d.abs() <= 1));
+    // both directions are used, unlike replacement which only flips the LSB
+    let raised = differences
+        .iter()
+        .zip(original.as_bytes())
+        .filter(|(&d, &value)| d == 1 && value % 2 == 1)
+        .count();
+    let lowered = differences
+        .iter()
+        .zip(original.as_bytes())
+        .filter(|(&d, &value)| d == -1 && value % 2 == 0)
+        .count();
+    assert!(raised > 0 && lowered > 0);
+}
+
+#[test]
+fn test_matching_saturates_at_range_limits() {
+    for value in [0, 255] {
+        let original = create_flat_image(40, 40, value);
+        let encoded = encode_matching(&original, &[0xA5; 100], 1);
+        for (&before, &after) in original.as_bytes().iter().zip(encoded.as_bytes()) {
+            assert!(before.abs_diff(after) <= 1);
+        }
+    }
+}
+
+#[test]
+fn test_matching_with_multiple_bits() {
+    let original = create_test_image(80, 80);
+    let payload: Vec<u8> = (0..1000).map(|i| (i * 13 % 256) as u8).collect();
+    for bits in [2, 3] {
+        let encoded = encode_matching(&original, &payload, bits);
+        let half_step = 1 << (bits - 1);
+        // away from the range limits the nearest value with the right low
+        // bits is at most half a step away
+        for (&before, &after) in original.as_bytes().iter().zip(encoded.as_bytes()) {
+            if (8..248).contains(&before) {
+                assert!(before.abs_diff(after) <= half_step);
+            }
+        }
+    }
+}
@@ -0,0 +1,117 @@
+// Tests for matrix embedding with Hamming codes
+
+mod common;
+
+use common::changed_samples;
+use image::{DynamicImage, Rgb, RgbImage};
+use img_stegano::{
+    decode_bytes, decode_bytes_with_options, encode_bytes, encode_bytes_with_options,
+    DecodeOptions, EncodeOptions, Image, ImgSteganoError,
+};
+
+fn create_test_image(width: u32, height: u32) -> DynamicImage {
+    DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| {
+        Rgb([
+            (x * 11 % 256) as u8,
+            (y * 7 % 256) as u8,
+            ((x * y) % 256) as u8,
+        ])
+    }))
+}
+
+fn matrix_options() -> EncodeOptions {
+    EncodeOptions::new().with_matrix_embedding(true)
+}
+
+#[test]
+fn test_matrix_round_trip() {
+    let payload = b"short note in a large cover";
+    let encoded = encode_bytes_with_options(
+        create_test_image(200, 200).into(),
+        payload,
+        &matrix_options(),
+    )
+    .unwrap();
+    assert_eq!(decode_bytes(&encoded).unwrap(), payload);
+}
+
+#[test]
+fn test_matrix_changes_fewer_samples() {
+    let original = create_test_image(200, 200);
+    let payload: Vec<u8> = (0..200).map(|i| (i * 37 % 256) as u8).collect();
+
+    let plain = encode_bytes(original.clone().into(), &payload).unwrap();
+    let matrix =
+        encode_bytes_with_options(original.clone().into(), &payload, &matrix_options()).unwrap();
+    let plain_changes = changed_samples(&original, &plain.into());
+    let matrix_changes = changed_samples(&original, &matrix.into());
+    // the header is embedded plainly, the body changes at most one sample
+    // per block of k bits
+    assert!(matrix_changes * 3 < plain_changes);
+}
+
+#[test]
+fn test_matrix_falls_back_to_small_codes_when_full() {
+    let image: Image = create_test_image(60, 60).into();
+    let payload: Vec<u8> = (0..image.capacity() - 3).map(|i| (i % 256) as u8).collect();
+    let encoded = encode_bytes_with_options(image, &payload, &matrix_options()).unwrap();
+    assert_eq!(decode_bytes(&encoded).unwrap(), payload);
+
+    let image: Image = create_test_image(60, 60).into();
+    let payload = vec![0u8; image.capacity()];
+    assert!(matches!(
+        encode_bytes_with_options(image, &payload, &matrix_options()),
+        Err(ImgSteganoError::MessageTooLarge { .. })
+    ));
+}
+
+#[test]
+fn test_matrix_rejects_error_correction() {
+    let options = matrix_options().with_error_correction(8);
+    let result = encode_bytes_with_options(create_test_image(60, 60).into(), b"x", &options);
+    assert!(matches!(
+        result,
+        Err(ImgSteganoError::InvalidOptions { .. })
+    ));
+}
+
+#[test]
+fn test_matrix_with_key_and_matching() {
+    let options = matrix_options()
+        .with_stego_key(b"hamming")
+        .with_lsb_matching(true)
+        .with_bits_per_channel(2);
+    let payload: Vec<u8> = (0..300).map(|i| (i % 256) as u8).collect();
+    let encoded =
+        encode_bytes_with_options(create_test_image(100, 100).into(), &payload, &options).unwrap();
+
+    let options = DecodeOptions::new().with_stego_key(b"hamming");
+    assert_eq!(
+        decode_bytes_with_options(&encoded, &options).unwrap(),
+        payload
+    );
+}
+
+#[test]
+fn test_matrix_rejects_oversized_length() {
+    // a header claiming 4 GiB coded with k = 16, written into the low bits
+    let mut header = b"ISTG\x01\x40".to_vec();
+    header.extend_from_slice(&u32::MAX.to_be_bytes());
+    header.extend_from_slice(&[0; 4]);
+    header.extend_from_slice(&[1, 16, 0]);
+    let mut image = create_test_image(60, 60);
+    let DynamicImage::ImageRgb8(buffer) = &mut image else {
+        unreachable!()
+    };
+    let bits = header
+        .iter()
+        .flat_map(|byte| (0..8).rev().map(move |i| (byte >> i) & 1));
+    for (sample, bit) in buffer.iter_mut().zip(bits) {
+        *sample = (*sample & !1) | bit;
+    }
+
+    assert!(matches!(
+        decode_bytes(&image.into()),
+        Err(ImgSteganoError::InvalidLength { .. })
+    ));
+}
@@ -0,0 +1,123 @@
+// Tests for pixel value differencing
+
+mod common;
+
+use common::create_half_textured_image;
+use image::{DynamicImage, GenericImageView, ImageBuffer, Rgb, RgbImage, Rgba, RgbaImage};
+use img_stegano::{decode_pvd, encode_bytes, encode_pvd, Image, ImgSteganoError};
+
+/// Mid-gray texture varying by up to 63 between neighbours
+fn create_textured_image(width: u32, height: u32) -> DynamicImage {
+    DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| {
+        let noise = (x.wrapping_mul(2654435761) ^ y.wrapping_mul(40503)) >> 7;
+        Rgb([
+            (96 + noise % 64) as u8,
+            (96 + noise / 64 % 64) as u8,
+            (96 + noise / 4096 % 64) as u8,
+        ])
+    }))
+}
+
+fn create_flat_image(width: u32, height: u32) -> DynamicImage {
+    DynamicImage::ImageRgb8(RgbImage::from_pixel(width, height, Rgb([128, 100, 60])))
+}
+
+#[test]
+fn test_pvd_round_trip() {
+    let payload: Vec<u8> = (0..2000).map(|i| (i * 7 % 256) as u8).collect();
+    let encoded = encode_pvd(create_half_textured_image(100, 100).into(), &payload).unwrap();
+    assert_eq!(decode_pvd(&encoded).unwrap(), payload);
+}
+
+#[test]
+fn test_pvd_capacity_depends_on_content() {
+    let flat: Image = create_flat_image(100, 100).into();
+    // 15000 pairs of 3 bits each, less the 12 byte header
+    assert_eq!(flat.pvd_capacity(), 15000 * 3 / 8 - 12);
+
+    let textured: Image = create_textured_image(100, 100).into();
+    assert!(textured.pvd_capacity() > flat.pvd_capacity() * 11 / 10);
+
+    let payload: Vec<u8> = (0..textured.pvd_capacity())
+        .map(|i| (i % 256) as u8)
+        .collect();
+    let encoded = encode_pvd(textured.clone(), &payload).unwrap();
+    assert_eq!(decode_pvd(&encoded).unwrap(), payload);
+
+    let payload = vec![0u8; textured.pvd_capacity() + 1];
+    assert!(matches!(
+        encode_pvd(textured, &payload),
+        Err(ImgSteganoError::MessageTooLarge { .. })
+    ));
+}
+
+#[test]
+fn test_pvd_changes_smooth_pairs_little() {
+    let original = create_half_textured_image(100, 100);
+    let payload = vec![0xFFu8; 3000];
+    let encoded: DynamicImage = encode_pvd(original.clone().into(), &payload)
+        .unwrap()
+        .into();
+
+    let mut largest_textured_change = 0;
+    for ((x, _, before), (_, _, after)) in original.pixels().zip(encoded.pixels()) {
+        for (&a, &b) in before.0.iter().zip(&after.0) {
+            if x < 50 {
+                // a difference of 0 becomes at most 7, split over the pair
+                assert!(a.abs_diff(b) <= 4);
+            } else {
+                largest_textured_change = largest_textured_change.max(a.abs_diff(b));
+            }
+        }
+    }
+    assert!(largest_textured_change > 4);
+}
+
+#[test]
+fn test_pvd_color_types() {
+    let payload = b"edges hold more";
+
+    let rgba = RgbaImage::from_fn(40, 40, |x, y| Rgba([(x * 7) as u8, (y * 5) as u8, 9, 200]));
+    let original = DynamicImage::ImageRgba8(rgba);
+    let encoded = encode_pvd(original.clone().into(), payload).unwrap();
+    assert_eq!(decode_pvd(&encoded).unwrap(), payload);
+    // alpha is never used
+    let encoded: DynamicImage = encoded.into();
+    for (before, after) in original.pixels().zip(encoded.pixels()) {
+        assert_eq!(before.2 .0[3], after.2 .0[3]);
+    }
+
+    let deep: ImageBuffer<Rgb<u16>, Vec<u16>> = ImageBuffer::from_fn(40, 40, |x, y| {
+        Rgb([(x * 1500) as u16, 30000, (y * 40) as u16])
+    });
+    let encoded = encode_pvd(DynamicImage::ImageRgb16(deep).into(), payload).unwrap();
+    assert_eq!(decode_pvd(&encoded).unwrap(), payload);
+}
+
+#[test]
+fn test_pvd_rejects_other_images() {
+    let plain = create_flat_image(60, 60);
+    assert!(matches!(
+        decode_pvd(&plain.clone().into()),
+        Err(ImgSteganoError::NoPayloadFound)
+    ));
+    let lsb = encode_bytes(plain.into(), b"not pvd").unwrap();
+    assert!(matches!(
+        decode_pvd(&lsb),
+        Err(ImgSteganoError::NoPayloadFound)
+    ));
+}
+
+#[test]
+fn test_pvd_rejects_truncated_payload() {
+    let payload = vec![0x3Cu8; 2000];
+    let encoded: DynamicImage = encode_pvd(create_half_textured_image(100, 100).into(), &payload)
+        .unwrap()
+        .into();
+    // the header stays in the first row, most of the payload is cut off
+    let cropped = encoded.crop_imm(0, 0, 100, 10);
+    assert!(matches!(
+        decode_pvd(&cropped.into()),
+        Err(ImgSteganoError::InvalidLength { length: 2000, .. })
+    ));
+}
@@ -0,0 +1,105 @@
+// Tests for public-key recipient encryption
+#![cfg(feature = "encrypt")]
+
+mod common;
+
+use common::{create_test_image, read_lsbs, write_lsbs};
+use image::DynamicImage;
+use img_stegano::{
+    decode_bytes, decode_encrypted, decode_with_key, encode_for_recipients,
+    generate_recipient_keypair, Image, ImgSteganoError, RecipientPublicKey, RecipientSecretKey,
+};
+
+#[test]
+fn test_single_recipient_round_trip() {
+    let (secret, public) = generate_recipient_keypair();
+    let payload = b"for your eyes only";
+
+    let encoded = encode_for_recipients(create_test_image(100, 100), payload, &[public])
+        .expect("Failed to encode for recipient");
+    let decoded = decode_with_key(&encoded, &secret).expect("Failed to decode with key");
+    assert_eq!(decoded, payload);
+}
+
+#[test]
+fn test_every_recipient_can_decode() {
+    let keys: Vec<_> = (0..3).map(|_| generate_recipient_keypair()).collect();
+    let publics: Vec<_> = keys.iter().map(|(_, public)| *public).collect();
+    let payload = b"team secret";
+
+    let encoded = encode_for_recipients(create_test_image(100, 100), payload, &publics)
+        .expect("Failed to encode for recipients");
+    for (secret, _) in &keys {
+        let decoded = decode_with_key(&encoded, secret).expect("Failed to decode with key");
+        assert_eq!(decoded, payload);
+    }
+}
+
+#[test]
+fn test_non_recipient_cannot_decode() {
+    let (_, public) = generate_recipient_keypair();
+    let (outsider, _) = generate_recipient_keypair();
+
+    let encoded = encode_for_recipients(create_test_image(100, 100), b"private", &[public])
+        .expect("Failed to encode for recipient");
+    let result = decode_with_key(&encoded, &outsider);
+    assert!(matches!(result, Err(ImgSteganoError::NotARecipient)));
+}
+
+#[test]
+fn test_other_decoders_require_key() {
+    let (_, public) = generate_recipient_keypair();
+    let encoded = encode_for_recipients(create_test_image(100, 100), b"private", &[public])
+        .expect("Failed to encode for recipient");
+
+    assert!(matches!(
+        decode_bytes(&encoded),
+        Err(ImgSteganoError::RecipientKeyRequired)
+    ));
+    assert!(matches!(
+        decode_encrypted(&encoded, "password"),
+        Err(ImgSteganoError::RecipientKeyRequired)
+    ));
+}
+
+#[test]
+fn test_no_recipients_rejected() {
+    let result = encode_for_recipients(create_test_image(100, 100), b"private", &[]);
+    assert!(matches!(
+        result,
+        Err(ImgSteganoError::InvalidRecipientCount { count: 0 })
+    ));
+}
+
+#[test]
+fn test_keys_round_trip_through_bytes() {
+    let (secret, public) = generate_recipient_keypair();
+    let restored_secret = RecipientSecretKey::from_bytes(secret.to_bytes());
+    let restored_public = RecipientPublicKey::from_bytes(public.to_bytes());
+
+    assert_eq!(restored_secret.public_key(), public);
+    assert_eq!(restored_public, public);
+}
+
+#[test]
+fn test_low_order_keys_rejected() {
+    // u = 0 is a point of small order, agreeing on it yields an all-zero secret
+    let low_order = RecipientPublicKey::from_bytes([0; 32]);
+    assert!(matches!(
+        encode_for_recipients(create_test_image(100, 100), b"x", &[low_order]),
+        Err(ImgSteganoError::InvalidPublicKey)
+    ));
+
+    // the stanza's ephemeral key follows the fixed header and stanza count
+    let (secret, public) = generate_recipient_keypair();
+    let encoded = DynamicImage::from(
+        encode_for_recipients(create_test_image(100, 100), b"x", &[public]).unwrap(),
+    );
+    let mut stream = read_lsbs(&encoded, 47);
+    stream[15..47].fill(0);
+    let forged: Image = write_lsbs(&encoded, &stream).into();
+    assert!(matches!(
+        decode_with_key(&forged, &secret),
+        Err(ImgSteganoError::InvalidPublicKey)
+    ));
+}
@@ -0,0 +1,129 @@
+// Tests for reversible embedding via histogram shifting
+
+use image::{DynamicImage, GrayImage, ImageBuffer, Luma, Rgb, RgbImage, Rgba, RgbaImage};
+use img_stegano::{encode_bytes, encode_reversible, extract_and_restore, Image, ImgSteganoError};
+
+/// Smooth gradient with a flat background, like a scan
+fn create_scan_image(width: u32, height: u32) -> DynamicImage {
+    DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| {
+        if y < height / 2 {
+            Rgb([12, 12, 12])
+        } else {
+            Rgb([(x * 2 % 256) as u8, (y % 256) as u8, ((x + y) % 256) as u8])
+        }
+    }))
+}
+
+/// Flat background next to noise using every sample value, so no
+/// histogram bin is empty
+fn create_noise_image(width: u32, height: u32) -> DynamicImage {
+    DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| {
+        if x < width / 4 {
+            return Rgb([90, 90, 90]);
+        }
+        let noise = (x.wrapping_mul(2654435761) ^ y.wrapping_mul(40503)) >> 5;
+        Rgb([noise as u8, (noise >> 8) as u8, (noise >> 16) as u8])
+    }))
+}
+
+fn assert_restores(original: DynamicImage, payload: &[u8]) {
+    let encoded = encode_reversible(original.clone().into(), payload).unwrap();
+    assert_ne!(DynamicImage::from(encoded.clone()), original);
+
+    let (decoded, restored) = extract_and_restore(&encoded).unwrap();
+    assert_eq!(decoded, payload);
+    assert_eq!(DynamicImage::from(restored), original);
+}
+
+#[test]
+fn test_reversible_restores_original() {
+    assert_restores(create_scan_image(100, 100), b"patient 0042, left knee");
+}
+
+#[test]
+fn test_reversible_with_occupied_zero_bin() {
+    let payload: Vec<u8> = (0..20).collect();
+    assert_restores(create_noise_image(120, 120), &payload);
+}
+
+#[test]
+fn test_reversible_color_types() {
+    let payload = b"exhibit B";
+    let gray = GrayImage::from_fn(80, 80, |x, y| {
+        Luma([if x < 40 { 200 } else { (x * y) as u8 }])
+    });
+    assert_restores(DynamicImage::ImageLuma8(gray), payload);
+
+    let rgba = RgbaImage::from_fn(60, 60, |x, y| Rgba([(x * 3) as u8, 40, (y * 3) as u8, 128]));
+    let original = DynamicImage::ImageRgba8(rgba);
+    let encoded: DynamicImage = encode_reversible(original.clone().into(), payload)
+        .unwrap()
+        .into();
+    // alpha is never used
+    for (before, after) in original
+        .as_bytes()
+        .chunks(4)
+        .zip(encoded.as_bytes().chunks(4))
+    {
+        assert_eq!(before[3], after[3]);
+    }
+    assert_restores(original, payload);
+
+    let deep: ImageBuffer<Rgb<u16>, Vec<u16>> = ImageBuffer::from_fn(60, 60, |x, y| {
+        Rgb([1000, (x * 700) as u16, (y * 900) as u16])
+    });
+    assert_restores(DynamicImage::ImageRgb16(deep), payload);
+}
+
+#[test]
+fn test_reversible_capacity() {
+    let image: Image = create_scan_image(100, 100).into();
+    let capacity = image.reversible_capacity();
+    // the flat half alone holds a bit in each of its 15000 samples
+    assert!(capacity > 15000 / 8 - 100);
+
+    let payload = vec![0xA7u8; capacity];
+    assert_restores(create_scan_image(100, 100), &payload);
+
+    let payload = vec![0xA7u8; capacity + 1];
+    assert!(matches!(
+        encode_reversible(image, &payload),
+        Err(ImgSteganoError::MessageTooLarge { .. })
+    ));
+}
+
+#[test]
+fn test_reversible_rejects_other_images() {
+    let plain = create_scan_image(60, 60);
+    assert!(matches!(
+        extract_and_restore(&plain.clone().into()),
+        Err(ImgSteganoError::NoPayloadFound)
+    ));
+    let lsb = encode_bytes(plain.into(), b"not reversible").unwrap();
+    assert!(matches!(
+        extract_and_restore(&lsb),
+        Err(ImgSteganoError::NoPayloadFound)
+    ));
+}
+
+#[test]
+fn test_reversible_detects_altered_original_bits() {
+    let encoded: DynamicImage = encode_reversible(create_scan_image(100, 100).into(), b"ward 3")
+        .unwrap()
+        .into();
+    let mut rgb = encoded.to_rgb8();
+    // the flat top rows carry the stream from sample 64 on, one bit per
+    // sample, and bits 64 to 127 hold the original low bits of the reserved
+    // samples, all zero here; turn one of them into a one bit
+    let samples: &mut [u8] = &mut rgb;
+    let marked = *samples[64..15000]
+        .iter()
+        .find(|&&value| value != 12)
+        .unwrap();
+    samples[130] = marked;
+
+    assert!(matches!(
+        extract_and_restore(&DynamicImage::ImageRgb8(rgb).into()),
+        Err(ImgSteganoError::ChecksumMismatch { .. })
+    ));
+}
@@ -0,0 +1,69 @@
+// Tests for k-of-n threshold secret sharing across images
+
+mod common;
+
+use common::create_test_image;
+use img_stegano::{decode_bytes, encode_shares, recover_from_images, Image, ImgSteganoError};
+
+fn covers(count: usize) -> Vec<Image> {
+    (0..count).map(|_| create_test_image(50, 50)).collect()
+}
+
+const SECRET: &[u8] = b"correct horse battery staple";
+
+#[test]
+fn test_any_threshold_subset_recovers() {
+    let shares = encode_shares(covers(5), SECRET, 3).expect("Failed to share secret");
+    assert_eq!(shares.len(), 5);
+
+    for subset in [[0, 1, 2], [0, 2, 4], [4, 3, 1], [1, 2, 3]] {
+        let images: Vec<Image> = subset.iter().map(|&i| shares[i].clone()).collect();
+        assert_eq!(
+            recover_from_images(&images).expect("Failed to recover secret"),
+            SECRET
+        );
+    }
+    assert_eq!(recover_from_images(&shares).unwrap(), SECRET);
+}
+
+#[test]
+fn test_too_few_shares() {
+    let shares = encode_shares(covers(4), SECRET, 3).unwrap();
+    let result = recover_from_images(&[shares[0].clone(), shares[3].clone(), shares[0].clone()]);
+    assert!(matches!(
+        result,
+        Err(ImgSteganoError::InsufficientShares {
+            available: 2,
+            threshold: 3
+        })
+    ));
+}
+
+#[test]
+fn test_single_share_does_not_contain_secret() {
+    let shares = encode_shares(covers(3), SECRET, 2).unwrap();
+    for share in &shares {
+        let body = decode_bytes(share).unwrap();
+        assert_eq!(body.len(), SECRET.len());
+        assert_ne!(body, SECRET);
+    }
+}
+
+#[test]
+fn test_shares_from_different_sets_rejected() {
+    let first = encode_shares(covers(3), SECRET, 2).unwrap();
+    let second = encode_shares(covers(3), SECRET, 2).unwrap();
+    let result = recover_from_images(&[first[0].clone(), second[1].clone()]);
+    assert!(matches!(result, Err(ImgSteganoError::ShareSetMismatch)));
+}
+
+#[test]
+fn test_invalid_threshold_rejected() {
+    for threshold in [0, 1, 4] {
+        let result = encode_shares(covers(3), SECRET, threshold);
+        assert!(matches!(
+            result,
+            Err(ImgSteganoError::InvalidOptions { .. })
+        ));
+    }
+}
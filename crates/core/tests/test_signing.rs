@@ -0,0 +1,130 @@
+// Tests for Ed25519 payload signatures
+#![cfg(feature = "sign")]
+
+mod common;
+
+use common::{create_test_image, flip_lsb, read_lsbs, write_lsbs};
+use image::DynamicImage;
+use img_stegano::{
+    decode_and_verify, decode_and_verify_with_options, decode_bytes, encode_bytes, encode_signed,
+    encode_signed_with_options, generate_signer_keypair, DecodeOptions, EncodeOptions, Image,
+    ImgSteganoError, SignerPublicKey, SignerSecretKey,
+};
+
+#[test]
+fn test_signed_round_trip() {
+    let (secret, public) = generate_signer_keypair();
+    let payload = b"signed, sealed, delivered";
+
+    let encoded =
+        encode_signed(create_test_image(100, 100), payload, &secret).expect("Failed to encode");
+    let (decoded, signer) = decode_and_verify(&encoded, &[public]).expect("Failed to verify");
+    assert_eq!(decoded, payload);
+    assert_eq!(signer, public);
+}
+
+#[test]
+fn test_signer_identified_among_trusted_keys() {
+    let alice = SignerSecretKey::generate();
+    let bob = SignerSecretKey::generate();
+
+    let encoded =
+        encode_signed(create_test_image(100, 100), b"from bob", &bob).expect("Failed to encode");
+    let (_, signer) = decode_and_verify(&encoded, &[alice.public_key(), bob.public_key()])
+        .expect("Failed to verify");
+    assert_eq!(signer, bob.public_key());
+}
+
+#[test]
+fn test_untrusted_signer_rejected() {
+    let mallory = SignerSecretKey::generate();
+    let alice = SignerSecretKey::generate();
+
+    let encoded =
+        encode_signed(create_test_image(100, 100), b"forged", &mallory).expect("Failed to encode");
+    let result = decode_and_verify(&encoded, &[alice.public_key()]);
+    assert!(matches!(result, Err(ImgSteganoError::UntrustedSigner)));
+}
+
+#[test]
+fn test_forged_signature_rejected() {
+    let secret = SignerSecretKey::generate();
+    let encoded =
+        encode_signed(create_test_image(100, 100), b"original", &secret).expect("Failed to encode");
+
+    // Flip a bit of the signature, which follows the 14 byte header and 32 byte key
+    let tampered = flip_lsb(encoded, (14 + 32) * 8 + 5);
+    let result = decode_and_verify(&tampered, &[secret.public_key()]);
+    assert!(matches!(result, Err(ImgSteganoError::InvalidSignature)));
+}
+
+#[test]
+fn test_unsigned_payload_rejected() {
+    let secret = SignerSecretKey::generate();
+    let encoded = encode_bytes(create_test_image(100, 100), b"unsigned").expect("Failed to encode");
+    let result = decode_and_verify(&encoded, &[secret.public_key()]);
+    assert!(matches!(result, Err(ImgSteganoError::SignatureMissing)));
+}
+
+#[test]
+fn test_signed_payload_still_readable() {
+    let secret = SignerSecretKey::generate();
+    let encoded =
+        encode_signed(create_test_image(100, 100), b"public", &secret).expect("Failed to encode");
+    let decoded = decode_bytes(&encoded).expect("Failed to decode");
+    assert_eq!(decoded, b"public");
+}
+
+#[test]
+fn test_keys_round_trip_through_bytes() {
+    let secret = SignerSecretKey::generate();
+    let restored = SignerSecretKey::from_bytes(secret.to_bytes());
+    let public =
+        SignerPublicKey::from_bytes(secret.public_key().to_bytes()).expect("Invalid public key");
+
+    assert_eq!(restored.public_key(), secret.public_key());
+    assert_eq!(public, secret.public_key());
+}
+
+#[test]
+fn test_signed_with_stego_key() {
+    let (secret, public) = generate_signer_keypair();
+    let payload = b"scattered and signed";
+    let options = EncodeOptions::new().with_stego_key(b"signing key");
+    let encoded =
+        encode_signed_with_options(create_test_image(100, 100), payload, &secret, &options)
+            .unwrap();
+    assert!(decode_and_verify(&encoded, &[public]).is_err());
+
+    let options = DecodeOptions::new().with_stego_key(b"signing key");
+    let (decoded, signer) = decode_and_verify_with_options(&encoded, &[public], &options).unwrap();
+    assert_eq!(decoded, payload);
+    assert_eq!(signer, public);
+}
+
+#[cfg(feature = "deflate")]
+#[test]
+fn test_signature_covers_compression_section() {
+    use img_stegano::Compression;
+
+    let (secret, public) = generate_signer_keypair();
+    let payload = vec![b'z'; 500];
+    let options = EncodeOptions::new().with_compression(Compression::Deflate);
+    let encoded =
+        encode_signed_with_options(create_test_image(100, 100), &payload, &secret, &options)
+            .unwrap();
+
+    // clear the compression flag and drop its section, which follows the
+    // 14 byte fixed header and the 96 byte signature section, so the
+    // compressed body would pass for the payload
+    let encoded = DynamicImage::from(encoded);
+    let mut stream = read_lsbs(&encoded, 200);
+    stream[5] &= !0b0000_1000;
+    stream.drain(110..115);
+    let tampered: Image = write_lsbs(&encoded, &stream).into();
+
+    assert!(matches!(
+        decode_and_verify(&tampered, &[public]),
+        Err(ImgSteganoError::InvalidSignature)
+    ));
+}
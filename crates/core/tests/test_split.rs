@@ -0,0 +1,80 @@
+// Tests for splitting one payload across several cover images
+
+mod common;
+
+use common::{create_test_image, payload};
+use img_stegano::{decode_bytes, decode_join, encode_bytes, encode_split, Image, ImgSteganoError};
+
+#[test]
+fn test_split_payload_larger_than_any_cover() {
+    let covers = vec![
+        create_test_image(40, 40),
+        create_test_image(60, 30),
+        create_test_image(50, 50),
+    ];
+    let largest = covers.iter().map(Image::capacity).max().unwrap();
+    let data = payload(largest * 2);
+
+    let shards = encode_split(covers, &data).expect("Failed to split payload");
+    assert_eq!(shards.len(), 3);
+    assert_eq!(decode_join(&shards).expect("Failed to join shards"), data);
+}
+
+#[test]
+fn test_join_in_any_order() {
+    let covers = (0..4).map(|_| create_test_image(40, 40)).collect();
+    let data = payload(1500);
+    let mut shards = encode_split(covers, &data).unwrap();
+
+    shards.reverse();
+    shards.swap(0, 2);
+    assert_eq!(decode_join(&shards).unwrap(), data);
+}
+
+#[test]
+fn test_join_reports_missing_shards() {
+    let covers = (0..5).map(|_| create_test_image(40, 40)).collect();
+    let shards = encode_split(covers, &payload(2000)).unwrap();
+
+    let partial = [shards[0].clone(), shards[2].clone(), shards[4].clone()];
+    let result = decode_join(&partial);
+    assert!(matches!(
+        result,
+        Err(ImgSteganoError::MissingShards { missing, count: 5 }) if missing == [1, 3]
+    ));
+}
+
+#[test]
+fn test_join_rejects_shards_from_different_sets() {
+    let first = encode_split(vec![create_test_image(40, 40); 2], &payload(900)).unwrap();
+    let second = encode_split(vec![create_test_image(40, 40); 2], &payload(900)).unwrap();
+
+    let mixed = [first[0].clone(), second[1].clone()];
+    assert!(matches!(
+        decode_join(&mixed),
+        Err(ImgSteganoError::ShardSetMismatch)
+    ));
+}
+
+#[test]
+fn test_split_too_large_for_all_covers() {
+    let covers = vec![create_test_image(10, 10), create_test_image(10, 10)];
+    let result = encode_split(covers, &payload(1000));
+    assert!(matches!(
+        result,
+        Err(ImgSteganoError::MessageTooLarge { .. })
+    ));
+}
+
+#[test]
+fn test_shard_and_plain_payloads_are_distinct() {
+    let shards = encode_split(vec![create_test_image(40, 40)], &payload(100)).unwrap();
+    // A single shard still carries the whole payload as its body
+    assert_eq!(decode_bytes(&shards[0]).unwrap(), payload(100));
+
+    let plain = encode_bytes(create_test_image(40, 40), b"plain").unwrap();
+    assert!(matches!(
+        decode_join(&[plain]),
+        Err(ImgSteganoError::NotAShard)
+    ));
+}
@@ -0,0 +1,114 @@
+// Tests for scattering the payload with a key-seeded traversal order
+
+use image::{DynamicImage, Rgb, RgbImage};
+use img_stegano::{
+    decode_bytes, decode_bytes_with_options, decode_with_report_with_options, encode_bytes,
+    encode_bytes_with_options, DecodeOptions, EncodeOptions, Image, ImgSteganoError,
+};
+
+fn create_test_image(width: u32, height: u32) -> DynamicImage {
+    DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| {
+        Rgb([
+            (x * 7 % 256) as u8,
+            (y * 3 % 256) as u8,
+            ((x ^ y) % 256) as u8,
+        ])
+    }))
+}
+
+/// Rows of the image containing at least one changed sample
+fn changed_rows(before: &DynamicImage, after: &DynamicImage) -> Vec<u32> {
+    let (before, after) = (before.to_rgb8(), after.to_rgb8());
+    (0..before.height())
+        .filter(|&y| (0..before.width()).any(|x| before.get_pixel(x, y) != after.get_pixel(x, y)))
+        .collect()
+}
+
+#[test]
+fn test_round_trip_with_key() {
+    let options = EncodeOptions::new().with_stego_key(b"correct horse");
+    let encoded =
+        encode_bytes_with_options(create_test_image(80, 80).into(), b"scattered", &options)
+            .unwrap();
+
+    let options = DecodeOptions::new().with_stego_key(b"correct horse");
+    assert_eq!(
+        decode_bytes_with_options(&encoded, &options).unwrap(),
+        b"scattered"
+    );
+}
+
+#[test]
+fn test_key_is_required() {
+    let options = EncodeOptions::new().with_stego_key(b"correct horse");
+    let encoded =
+        encode_bytes_with_options(create_test_image(80, 80).into(), b"hidden", &options).unwrap();
+
+    assert!(matches!(
+        decode_bytes(&encoded),
+        Err(ImgSteganoError::NoPayloadFound)
+    ));
+    let wrong = DecodeOptions::new().with_stego_key(b"battery staple");
+    assert!(matches!(
+        decode_bytes_with_options(&encoded, &wrong),
+        Err(ImgSteganoError::NoPayloadFound)
+    ));
+}
+
+#[test]
+fn test_changes_are_spread_over_the_image() {
+    let original = create_test_image(100, 100);
+    let payload = [0x5Au8; 64];
+
+    let sequential = encode_bytes(original.clone().into(), &payload).unwrap();
+    let rows = changed_rows(&original, &sequential.into());
+    assert!(*rows.last().unwrap() < 10);
+
+    let options = EncodeOptions::new().with_stego_key(b"spread");
+    let keyed = encode_bytes_with_options(original.clone().into(), &payload, &options).unwrap();
+    let rows = changed_rows(&original, &keyed.into());
+    assert!(rows.len() > 50);
+    assert!(*rows.first().unwrap() < 10 && *rows.last().unwrap() > 90);
+}
+
+#[test]
+fn test_key_combines_with_layout_and_error_correction() {
+    let image: Image = create_test_image(120, 120).into();
+    let payload: Vec<u8> = (0..=255).collect();
+    let options = EncodeOptions::new()
+        .with_stego_key(b"layered")
+        .with_bits_per_channel(2)
+        .with_error_correction(16);
+    let encoded = encode_bytes_with_options(image, &payload, &options).unwrap();
+
+    let options = DecodeOptions::new().with_stego_key(b"layered");
+    assert_eq!(
+        decode_bytes_with_options(&encoded, &options).unwrap(),
+        payload
+    );
+}
+
+#[test]
+fn test_same_key_gives_same_image() {
+    let options = EncodeOptions::new().with_stego_key(b"deterministic");
+    let encode = || -> DynamicImage {
+        encode_bytes_with_options(create_test_image(60, 60).into(), b"repeat", &options)
+            .unwrap()
+            .into()
+    };
+    assert_eq!(encode().as_bytes(), encode().as_bytes());
+}
+
+#[test]
+fn test_report_with_key() {
+    let options = EncodeOptions::new()
+        .with_stego_key(b"reported")
+        .with_error_correction(8);
+    let encoded =
+        encode_bytes_with_options(create_test_image(80, 80).into(), b"repaired", &options).unwrap();
+
+    let options = DecodeOptions::new().with_stego_key(b"reported");
+    let (decoded, report) = decode_with_report_with_options(&encoded, &options).unwrap();
+    assert_eq!(decoded, b"repaired");
+    assert_eq!(report.corrected_errors, 0);
+}
@@ -0,0 +1,159 @@
+// Tests for the streaming Read/Write interface
+
+mod common;
+
+use common::{create_test_image, payload};
+use std::io::{self, Read, Write};
+
+use image::DynamicImage;
+use img_stegano::{
+    decode_bytes, decode_bytes_with_options, encode_bytes, encode_bytes_with_options, Channels,
+    DecodeOptions, EncodeOptions, Image, ImgSteganoError, StegoReader, StegoWriter,
+};
+
+#[test]
+fn test_stream_round_trip() {
+    let data = payload(3000);
+    let mut image = create_test_image(100, 100);
+
+    let mut writer = StegoWriter::new(&mut image);
+    // odd sized chunks exercise writes that start mid-pixel
+    for chunk in data.chunks(333) {
+        writer.write_all(chunk).expect("Failed to write chunk");
+    }
+    assert_eq!(writer.finish().expect("Failed to finish"), data.len());
+
+    let mut reader = StegoReader::new(&image).expect("Failed to open reader");
+    assert_eq!(reader.remaining(), data.len());
+    let mut decoded = Vec::new();
+    io::copy(&mut reader, &mut decoded).expect("Failed to read payload");
+    assert_eq!(decoded, data);
+}
+
+#[test]
+fn test_streams_interoperate_with_byte_functions() {
+    let data = payload(700);
+    let mut image = create_test_image(100, 100);
+    let mut writer = StegoWriter::new(&mut image);
+    io::copy(&mut data.as_slice(), &mut writer).unwrap();
+    writer.finish().unwrap();
+    assert_eq!(decode_bytes(&image).unwrap(), data);
+
+    let encoded = encode_bytes(create_test_image(100, 100), &data).unwrap();
+    let mut decoded = Vec::new();
+    StegoReader::new(&encoded)
+        .unwrap()
+        .read_to_end(&mut decoded)
+        .unwrap();
+    assert_eq!(decoded, data);
+}
+
+#[test]
+fn test_writer_stops_at_capacity() {
+    let mut image = create_test_image(10, 10);
+    let capacity = image.capacity();
+    let mut writer = StegoWriter::new(&mut image);
+
+    let error = writer.write_all(&payload(capacity + 1)).unwrap_err();
+    let inner = error.into_inner().unwrap();
+    assert!(matches!(
+        inner.downcast_ref::<ImgSteganoError>(),
+        Some(ImgSteganoError::MessageTooLarge { .. })
+    ));
+    assert_eq!(writer.remaining(), 0);
+}
+
+#[test]
+fn test_unfinished_writer_leaves_no_payload() {
+    let mut image = encode_bytes(create_test_image(50, 50), b"old message").unwrap();
+    {
+        // dropped without calling finish
+        let mut writer = StegoWriter::new(&mut image);
+        writer.write_all(b"new message").unwrap();
+    }
+    assert!(matches!(
+        decode_bytes(&image),
+        Err(ImgSteganoError::NoPayloadFound)
+    ));
+
+    let writer = StegoWriter::new(&mut image);
+    assert!(matches!(
+        writer.finish(),
+        Err(ImgSteganoError::EmptyMessage)
+    ));
+}
+
+#[test]
+fn test_reader_detects_corruption_at_end() {
+    let encoded = encode_bytes(create_test_image(50, 50), &payload(200)).unwrap();
+    // flip the LSB of a pixel inside the payload body
+    let mut image: DynamicImage = encoded.into();
+    let mut rgb = image.to_rgb8();
+    rgb.get_pixel_mut(0, 2).0[0] ^= 1;
+    image = DynamicImage::ImageRgb8(rgb);
+    let encoded: Image = image.into();
+
+    let mut decoded = Vec::new();
+    let error = StegoReader::new(&encoded)
+        .unwrap()
+        .read_to_end(&mut decoded)
+        .unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn test_reader_handles_error_corrected_payload() {
+    let data = payload(300);
+    let options = EncodeOptions::new().with_error_correction(16);
+    let encoded = encode_bytes_with_options(create_test_image(100, 100), &data, &options).unwrap();
+
+    let mut decoded = Vec::new();
+    StegoReader::new(&encoded)
+        .unwrap()
+        .read_to_end(&mut decoded)
+        .unwrap();
+    assert_eq!(decoded, data);
+}
+
+#[test]
+fn test_stream_with_options() {
+    let data = payload(2000);
+    let options = EncodeOptions::new()
+        .with_stego_key(b"streamed")
+        .with_bits_per_channel(3)
+        .with_channels(Channels::RED | Channels::BLUE)
+        .with_lsb_matching(true);
+    let mut image = create_test_image(100, 100);
+    let capacity = image.capacity_with_options(&options);
+
+    let mut writer = StegoWriter::with_options(&mut image, &options).unwrap();
+    assert_eq!(writer.remaining(), capacity);
+    // 3 bits per channel leave most chunks ending mid-slot
+    for chunk in data.chunks(101) {
+        writer.write_all(chunk).unwrap();
+    }
+    writer.finish().unwrap();
+
+    let options = DecodeOptions::new().with_stego_key(b"streamed");
+    assert_eq!(decode_bytes_with_options(&image, &options).unwrap(), data);
+    let mut decoded = Vec::new();
+    StegoReader::with_options(&image, &options)
+        .unwrap()
+        .read_to_end(&mut decoded)
+        .unwrap();
+    assert_eq!(decoded, data);
+    assert!(matches!(
+        StegoReader::new(&image),
+        Err(ImgSteganoError::NoPayloadFound)
+    ));
+}
+
+#[test]
+fn test_writer_rejects_buffered_options() {
+    let mut image = create_test_image(50, 50);
+    let options = EncodeOptions::new().with_error_correction(8);
+    assert!(matches!(
+        StegoWriter::with_options(&mut image, &options),
+        Err(ImgSteganoError::InvalidOptions { .. })
+    ));
+}
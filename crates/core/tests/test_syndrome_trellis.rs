@@ -0,0 +1,236 @@
+// Tests for syndrome-trellis coding
+
+mod common;
+
+use common::{changed_samples, create_half_textured_image};
+use image::{DynamicImage, GenericImageView, Rgb, RgbImage};
+use img_stegano::{
+    decode_bytes, decode_bytes_with_options, encode_bytes, encode_bytes_with_options,
+    DecodeOptions, Distortion, EncodeOptions, Image, ImgSteganoError,
+};
+
+fn create_test_image(width: u32, height: u32) -> DynamicImage {
+    DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| {
+        Rgb([
+            (x * 11 % 256) as u8,
+            (y * 7 % 256) as u8,
+            ((x * y) % 256) as u8,
+        ])
+    }))
+}
+
+fn trellis_options(distortion: Distortion) -> EncodeOptions {
+    EncodeOptions::new().with_syndrome_trellis(distortion)
+}
+
+#[test]
+fn test_trellis_round_trip() {
+    let payload: Vec<u8> = (0..300).map(|i| (i * 7 % 256) as u8).collect();
+    for distortion in [Distortion::Uniform, Distortion::Hill, Distortion::Adaptive] {
+        let encoded = encode_bytes_with_options(
+            create_test_image(100, 100).into(),
+            &payload,
+            &trellis_options(distortion),
+        )
+        .unwrap();
+        assert_eq!(decode_bytes(&encoded).unwrap(), payload);
+    }
+}
+
+#[test]
+fn test_trellis_changes_fewer_samples() {
+    let original = create_test_image(200, 200);
+    let payload: Vec<u8> = (0..200).map(|i| (i * 37 % 256) as u8).collect();
+
+    let plain = encode_bytes(original.clone().into(), &payload).unwrap();
+    let trellis = encode_bytes_with_options(
+        original.clone().into(),
+        &payload,
+        &trellis_options(Distortion::Uniform),
+    )
+    .unwrap();
+    let plain_changes = changed_samples(&original, &plain.into());
+    let trellis_changes = changed_samples(&original, &trellis.into());
+    // plain embedding changes every other sample, the trellis embeds
+    // several message bits per change
+    assert!(trellis_changes * 2 < plain_changes);
+}
+
+#[test]
+fn test_trellis_hill_avoids_flat_regions() {
+    let original = create_half_textured_image(100, 100);
+    let payload = vec![0x5Au8; 200];
+    let encoded: DynamicImage = encode_bytes_with_options(
+        original.clone().into(),
+        &payload,
+        &trellis_options(Distortion::Hill),
+    )
+    .unwrap()
+    .into();
+
+    // only the header sits in the first row, the body changes stay in the
+    // texture or next to it where the costs are smoothed
+    for ((x, y, before), (_, _, after)) in original.pixels().zip(encoded.pixels()) {
+        if y > 0 && before != after {
+            assert!(x >= 40, "flat pixel ({x}, {y}) was changed");
+        }
+    }
+    assert_eq!(decode_bytes(&encoded.into()).unwrap(), payload);
+}
+
+#[test]
+fn test_trellis_fills_capacity() {
+    let image: Image = create_test_image(60, 60).into();
+    // three bytes of the capacity go to the coding section
+    let payload: Vec<u8> = (0..image.capacity() - 3).map(|i| (i % 256) as u8).collect();
+    let options = trellis_options(Distortion::Uniform);
+    let encoded = encode_bytes_with_options(image, &payload, &options).unwrap();
+    assert_eq!(decode_bytes(&encoded).unwrap(), payload);
+
+    let image: Image = create_test_image(60, 60).into();
+    let payload = vec![0u8; image.capacity()];
+    assert!(matches!(
+        encode_bytes_with_options(image, &payload, &options),
+        Err(ImgSteganoError::MessageTooLarge { .. })
+    ));
+}
+
+#[test]
+fn test_trellis_with_key_and_adaptive_embedding() {
+    let options = trellis_options(Distortion::Adaptive)
+        .with_adaptive_embedding(true)
+        .with_stego_key(b"trellis")
+        .with_bits_per_channel(2);
+    let payload: Vec<u8> = (0..300).map(|i| (i % 256) as u8).collect();
+    let encoded = encode_bytes_with_options(
+        create_half_textured_image(100, 100).into(),
+        &payload,
+        &options,
+    )
+    .unwrap();
+
+    let options = DecodeOptions::new().with_stego_key(b"trellis");
+    assert_eq!(
+        decode_bytes_with_options(&encoded, &options).unwrap(),
+        payload
+    );
+}
+
+#[test]
+fn test_trellis_rejects_matrix_embedding() {
+    let options = trellis_options(Distortion::Uniform).with_matrix_embedding(true);
+    let result = encode_bytes_with_options(create_test_image(60, 60).into(), b"x", &options);
+    assert!(matches!(
+        result,
+        Err(ImgSteganoError::InvalidOptions { .. })
+    ));
+}
+
+#[test]
+fn test_trellis_custom_distortion() {
+    let original = create_test_image(100, 100);
+    // changes are cheap in even columns only
+    let distortion = Distortion::custom(|image| {
+        let (width, height) = image.dimensions();
+        (0..width * height * 3)
+            .map(|sample| {
+                if sample / 3 % width % 2 == 0 {
+                    1.0
+                } else {
+                    1e6
+                }
+            })
+            .collect()
+    });
+    let payload: Vec<u8> = (0..300).map(|i| (i * 13 % 256) as u8).collect();
+    let encoded: DynamicImage = encode_bytes_with_options(
+        original.clone().into(),
+        &payload,
+        &trellis_options(distortion),
+    )
+    .unwrap()
+    .into();
+
+    // the first row holds the header
+    for ((x, y, before), (_, _, after)) in original.pixels().zip(encoded.pixels()) {
+        if y > 0 && before != after {
+            assert_eq!(x % 2, 0, "odd pixel ({x}, {y}) was changed");
+        }
+    }
+    assert_eq!(decode_bytes(&encoded.into()).unwrap(), payload);
+}
+
+#[test]
+fn test_trellis_custom_distortion_needs_every_sample() {
+    let distortion = Distortion::custom(|image| vec![1.0; image.as_bytes().len() / 2]);
+    let result = encode_bytes_with_options(
+        create_test_image(60, 60).into(),
+        b"too few costs",
+        &trellis_options(distortion),
+    );
+    assert!(matches!(
+        result,
+        Err(ImgSteganoError::InvalidOptions { .. })
+    ));
+}
+
+/// Costs of `expensive` on the left half of an RGB image and 1 elsewhere
+fn left_half_costs(expensive: f32) -> Distortion {
+    Distortion::custom(move |image| {
+        let (width, height) = image.dimensions();
+        (0..width * height * 3)
+            .map(|sample| {
+                if sample / 3 % width < width / 2 {
+                    expensive
+                } else {
+                    1.0
+                }
+            })
+            .collect()
+    })
+}
+
+#[test]
+fn test_trellis_rejects_infinite_costs() {
+    let payload = vec![0x96u8; 200];
+    let result = encode_bytes_with_options(
+        create_test_image(64, 64).into(),
+        &payload,
+        &trellis_options(left_half_costs(f32::INFINITY)),
+    );
+    assert!(matches!(
+        result,
+        Err(ImgSteganoError::InvalidOptions { .. })
+    ));
+}
+
+#[test]
+fn test_trellis_with_huge_costs() {
+    // sums of these overflow an f32 long before the end of the trellis
+    let payload = vec![0x96u8; 200];
+    let encoded = encode_bytes_with_options(
+        create_test_image(64, 64).into(),
+        &payload,
+        &trellis_options(left_half_costs(f32::MAX)),
+    )
+    .unwrap();
+    assert_eq!(decode_bytes(&encoded).unwrap(), payload);
+}
+
+#[test]
+fn test_trellis_long_payload() {
+    // several thousand message bits, searched in more than one segment
+    let original = create_test_image(200, 200);
+    let payload: Vec<u8> = (0..4000).map(|i| (i * 29 % 256) as u8).collect();
+    let plain = encode_bytes(original.clone().into(), &payload).unwrap();
+    let trellis = encode_bytes_with_options(
+        original.clone().into(),
+        &payload,
+        &trellis_options(Distortion::Uniform),
+    )
+    .unwrap();
+    assert_eq!(decode_bytes(&trellis).unwrap(), payload);
+    assert!(
+        changed_samples(&original, &trellis.into()) < changed_samples(&original, &plain.into())
+    );
+}